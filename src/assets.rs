@@ -0,0 +1,432 @@
+pub mod assets {
+    use std::collections::BTreeMap;
+    use std::io::{Error, ErrorKind};
+    use std::path::{Path, PathBuf};
+    use std::time::SystemTime;
+    use crate::level::level::LevelError;
+    use macroquad::prelude::{Image, Rect, Texture2D};
+    use crate::map::world::{Texture, AssetError};
+
+    /*
+        Pack file, every asset in one file so the game doesn't depend on the working directory
+
+            magic "RCPK", version u32, entry count u32
+            table of contents, per entry: name length u16, name, offset u64, size u64
+            file data, offsets are from the start of the pack
+
+        Numbers are little endian, names are the paths levels and manifests use, like src/assets/sky.png
+    */
+    pub struct Pack {
+        data: Vec<u8>,
+        entries: BTreeMap<String, (usize, usize)>, // Name to offset and size
+    }
+
+    const PACK_MAGIC: &[u8; 4] = b"RCPK";
+    const PACK_VERSION: u32 = 1;
+
+    // Reads the pack table of contents in order
+    struct Reader<'a> {
+        data: &'a [u8],
+        at: usize,
+    }
+
+    impl<'a> Reader<'a> {
+        fn take(&mut self, len: usize) -> Result<&'a [u8], Error> {
+            if self.data.len() - self.at < len { return Err(invalid("pack ends early")); }
+            self.at += len;
+            Ok(&self.data[self.at - len..self.at])
+        }
+
+        fn u16(&mut self) -> Result<u16, Error> { Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap())) }
+        fn u32(&mut self) -> Result<u32, Error> { Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap())) }
+        fn u64(&mut self) -> Result<u64, Error> { Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap())) }
+    }
+
+    fn invalid(message: &str) -> Error {
+        Error::new(ErrorKind::InvalidData, message)
+    }
+
+    impl Pack {
+        pub fn parse(data: Vec<u8>) -> Result<Pack, Error> {
+            let mut entries: BTreeMap<String, (usize, usize)> = BTreeMap::new();
+            let mut reader: Reader = Reader { data: &data, at: 0 };
+            if reader.take(4)? != PACK_MAGIC { return Err(invalid("not a pack file")); }
+            let version: u32 = reader.u32()?;
+            if version != PACK_VERSION { return Err(invalid(&format!("pack version {} isn't supported", version))); }
+
+            for _ in 0..reader.u32()? {
+                let len: usize = reader.u16()? as usize;
+                let name: String = String::from_utf8(reader.take(len)?.to_vec()).map_err(|_| invalid("pack entry name isn't UTF-8"))?;
+                let (offset, size): (u64, u64) = (reader.u64()?, reader.u64()?);
+                if offset.checked_add(size).is_none_or(|end| end > data.len() as u64) {
+                    return Err(invalid(&format!("pack entry {} is past the end of the pack", name)));
+                }
+                entries.insert(name, (offset as usize, size as usize));
+            }
+            Ok(Pack { data: data, entries: entries })
+        }
+
+        pub fn load(path: &str) -> Result<Pack, Error> {
+            Pack::parse(std::fs::read(path)?)
+        }
+
+        // Pack file bytes for the files given as (name, data)
+        pub fn build(files: &[(String, Vec<u8>)]) -> Vec<u8> {
+            let toc: usize = files.iter().map(|(name, _)| 2 + name.len() + 16).sum();
+            let mut offset: usize = 12 + toc;
+            let mut pack: Vec<u8> = Vec::new();
+            pack.extend_from_slice(PACK_MAGIC);
+            pack.extend_from_slice(&PACK_VERSION.to_le_bytes());
+            pack.extend_from_slice(&(files.len() as u32).to_le_bytes());
+            for (name, data) in files {
+                pack.extend_from_slice(&(name.len() as u16).to_le_bytes());
+                pack.extend_from_slice(name.as_bytes());
+                pack.extend_from_slice(&(offset as u64).to_le_bytes());
+                pack.extend_from_slice(&(data.len() as u64).to_le_bytes());
+                offset += data.len();
+            }
+            for (_, data) in files { pack.extend_from_slice(data); }
+            pack
+        }
+
+        pub fn get(&self, name: &str) -> Option<&[u8]> {
+            self.entries.get(name).map(|&(offset, size)| &self.data[offset..offset + size])
+        }
+
+        pub fn names(&self) -> impl Iterator<Item = &str> {
+            self.entries.keys().map(|n| n.as_str())
+        }
+    }
+
+    /*
+        Where levels, manifests and images are read from, a directory the paths are relative to, a pack,
+        files built into the binary, or layers of these where the first one that has a file wins
+    */
+    pub enum AssetSource {
+        Dir(PathBuf),
+        Pack(Pack),
+        Embedded(&'static [(&'static str, &'static [u8])]), // Path and contents
+        Layers(Vec<AssetSource>),
+    }
+
+    /*
+        The demo level and everything it uses, built into the binary with the embedded-assets feature
+        so it runs from any directory
+    */
+    #[cfg(feature = "embedded-assets")]
+    pub const EMBEDDED: &[(&str, &[u8])] = &[
+        ("src/assets/levels/demo.map", include_bytes!("assets/levels/demo.map")),
+        ("src/assets/manifest.txt", include_bytes!("assets/manifest.txt")),
+        ("src/assets/barrel.png", include_bytes!("assets/barrel.png")),
+        ("src/assets/button_off.png", include_bytes!("assets/button_off.png")),
+        ("src/assets/button_on.png", include_bytes!("assets/button_on.png")),
+        ("src/assets/concrete_pattern.png", include_bytes!("assets/concrete_pattern.png")),
+        ("src/assets/floral_pattern.png", include_bytes!("assets/floral_pattern.png")),
+        ("src/assets/greenlight.png", include_bytes!("assets/greenlight.png")),
+        ("src/assets/jerma.png", include_bytes!("assets/jerma.png")),
+        ("src/assets/log_plank.png", include_bytes!("assets/log_plank.png")),
+        ("src/assets/metal_double_door.png", include_bytes!("assets/metal_double_door.png")),
+        ("src/assets/metal_floor.png", include_bytes!("assets/metal_floor.png")),
+        ("src/assets/mossy_cobble.png", include_bytes!("assets/mossy_cobble.png")),
+        ("src/assets/pillar.png", include_bytes!("assets/pillar.png")),
+        ("src/assets/red_brick.png", include_bytes!("assets/red_brick.png")),
+        ("src/assets/rose_dark.png", include_bytes!("assets/rose_dark.png")),
+        ("src/assets/rose_pattern.png", include_bytes!("assets/rose_pattern.png")),
+        ("src/assets/sky.png", include_bytes!("assets/sky.png")),
+        ("src/assets/smooth_concrete.png", include_bytes!("assets/smooth_concrete.png")),
+        ("src/assets/wood_plank.png", include_bytes!("assets/wood_plank.png")),
+        ("src/assets/wooden_double_door.png", include_bytes!("assets/wooden_double_door.png")),
+    ];
+
+    impl Default for AssetSource {
+        // The working directory, where the paths in the demo level point from the repo root
+        fn default() -> AssetSource {
+            AssetSource::Dir(PathBuf::from("."))
+        }
+    }
+
+    impl AssetSource {
+        // A directory, or a pack file
+        pub fn open(path: &str) -> Result<AssetSource, Error> {
+            if std::fs::metadata(path)?.is_dir() { Ok(AssetSource::Dir(PathBuf::from(path))) }
+            else { Ok(AssetSource::Pack(Pack::load(path)?)) }
+        }
+
+        pub fn read(&self, path: &str) -> Result<Vec<u8>, Error> {
+            match self {
+                AssetSource::Dir(dir) => std::fs::read(dir.join(path)),
+                AssetSource::Pack(pack) => pack.get(path).map(|d| d.to_vec()).ok_or(Error::new(ErrorKind::NotFound, "not in the pack")),
+                AssetSource::Embedded(files) => files.iter().find(|(p, _)| *p == path).map(|(_, d)| d.to_vec())
+                    .ok_or(Error::new(ErrorKind::NotFound, "not built into the game")),
+                AssetSource::Layers(layers) => {
+                    // Only a missing file falls through, a file that's there but unreadable is an error
+                    let mut error: Error = Error::new(ErrorKind::NotFound, "no asset sources");
+                    for layer in layers {
+                        match layer.read(path) {
+                            Err(e) if e.kind() == ErrorKind::NotFound => error = e,
+                            result => return result,
+                        }
+                    }
+                    Err(error)
+                }
+            }
+        }
+
+        pub fn read_to_string(&self, path: &str) -> Result<String, Error> {
+            String::from_utf8(self.read(path)?).map_err(|_| invalid("not UTF-8 text"))
+        }
+
+        // Directory files are read from first, if there is one, packs and embedded files can't change
+        pub fn dir(&self) -> Option<&Path> {
+            match self {
+                AssetSource::Dir(dir) => Some(dir),
+                AssetSource::Layers(layers) => layers.first().and_then(|l| l.dir()),
+                AssetSource::Pack(_) | AssetSource::Embedded(_) => None,
+            }
+        }
+    }
+
+    const POLL_TIME: f32 = 0.5; // Seconds between checking files for changes
+
+    /*
+        Hot reloading, watches files for changes by polling their modified times
+    */
+    pub struct Watcher {
+        dir: PathBuf,
+        files: Vec<(String, Option<SystemTime>)>, // Path and modified time, None while it doesn't exist
+        timer: f32,
+    }
+
+    fn modified(path: &Path) -> Option<SystemTime> {
+        std::fs::metadata(path).and_then(|m| m.modified()).ok()
+    }
+
+    impl Watcher {
+        // Watches files, with paths relative to dir
+        pub fn new(dir: &Path, files: &[String]) -> Watcher {
+            Watcher {
+                dir: dir.to_path_buf(),
+                files: files.iter().map(|f| (f.clone(), modified(&dir.join(f)))).collect(),
+                timer: 0.0,
+            }
+        }
+
+        // Files changed, created or deleted since they were last checked, only checks every POLL_TIME seconds
+        pub fn poll(&mut self, dt: f32) -> Vec<String> {
+            self.timer += dt;
+            if self.timer < POLL_TIME { return Vec::new(); }
+            self.timer = 0.0;
+            self.changed()
+        }
+
+        // Checks every file now
+        pub fn changed(&mut self) -> Vec<String> {
+            let mut changed: Vec<String> = Vec::new();
+            for (file, time) in &mut self.files {
+                let now: Option<SystemTime> = modified(&self.dir.join(&*file));
+                if now != *time {
+                    *time = now;
+                    changed.push(file.clone());
+                }
+            }
+            changed
+        }
+    }
+
+    /*
+        Asset manifest, names the image files levels refer to
+        One asset per line, anything after a # is a comment
+
+            # <name> <path>
+            red_brick   src/assets/red_brick.png
+    */
+    pub struct Manifest {
+        paths: BTreeMap<String, String>, // Name to path
+    }
+
+    impl Manifest {
+        pub fn parse(source: &str) -> Result<Manifest, LevelError> {
+            let mut paths: BTreeMap<String, String> = BTreeMap::new();
+            for (i, line) in source.lines().enumerate() {
+                let text: &str = line.split('#').next().unwrap();
+                let words: Vec<&str> = text.split_whitespace().collect();
+                if words.is_empty() { continue; }
+
+                // Words are slices of the line, their offset in it gives the column
+                let error = |word: &str, message: String| LevelError::Parse { 
+                    line: i + 1, 
+                    column: text[..word.as_ptr() as usize - text.as_ptr() as usize].chars().count() + 1, 
+                    message: message,
+                };
+                if words.len() != 2 {
+                    let at: &str = if words.len() > 2 { words[2] } else { words[0] };
+                    return Err(error(at, String::from("expected '<name> <path>'")));
+                }
+                if paths.insert(words[0].to_string(), words[1].to_string()).is_some() {
+                    return Err(error(words[0], format!("'{}' is defined twice", words[0])));
+                }
+            }
+            Ok(Manifest { paths: paths })
+        }
+
+        pub fn load(assets: &AssetSource, path: &str) -> Result<Manifest, LevelError> {
+            let source: String = assets.read_to_string(path).map_err(|e| LevelError::File(path.to_string(), e))?;
+            Manifest::parse(&source).map_err(|e| LevelError::Manifest(path.to_string(), Box::new(e)))
+        }
+
+        pub fn path(&self, name: &str) -> Option<&str> {
+            self.paths.get(name).map(|p| p.as_str())
+        }
+
+        pub fn names(&self) -> impl Iterator<Item = &str> {
+            self.paths.keys().map(|n| n.as_str())
+        }
+    }
+
+    /*
+        Wall textures by name and by the id the map uses for them, ids start at 1 as 0 is an empty cell
+    */
+    #[derive(Default)]
+    pub struct TextureRegistry {
+        textures: Vec<Texture>, // Indexed by id - 1
+        names: Vec<String>, // Indexed by id - 1
+    }
+
+    impl TextureRegistry {
+        // Adds a texture under the next id and returns it, names have to be unique
+        pub fn add(&mut self, name: &str, texture: Texture) -> u32 {
+            assert!(self.id(name).is_none(), "texture '{}' is already registered", name);
+            self.textures.push(texture);
+            self.names.push(name.to_string());
+            self.textures.len() as u32
+        }
+
+        pub fn id(&self, name: &str) -> Option<u32> {
+            self.names.iter().position(|n| n == name).map(|i| i as u32 + 1)
+        }
+
+        pub fn name(&self, id: u32) -> &str {
+            &self.names[(id - 1) as usize]
+        }
+
+        pub fn get(&self, id: u32) -> &Texture {
+            &self.textures[(id - 1) as usize]
+        }
+
+        pub fn len(&self) -> usize {
+            self.textures.len()
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.textures.is_empty()
+        }
+
+        // In id order
+        pub fn iter(&self) -> impl Iterator<Item = &Texture> {
+            self.textures.iter()
+        }
+
+        pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Texture> {
+            self.textures.iter_mut()
+        }
+    }
+
+    const ATLAS_PADDING: u32 = 1; // Edge texels repeated around each texture so filtering doesn't pick up its neighbours
+
+    /*
+        Wall and sprite textures packed into one image, so Renderer draws every wall and sprite from
+        a single GPU texture. Textures can be any size, they're packed in rows, tallest first
+    */
+    pub struct Atlas {
+        pub image: Image,
+        pub texture: Texture2D, // Empty until uploaded to the GPU
+        walls: Vec<Rect>, // Indexed by texture id - 1
+        sprites: Vec<Rect>, // Indexed like World::sprite_textures
+    }
+
+    impl Default for Atlas {
+        fn default() -> Atlas {
+            Atlas { image: Image::empty(), texture: Texture2D::empty(), walls: Vec::new(), sprites: Vec::new() }
+        }
+    }
+
+    impl Atlas {
+        // Fails if the packed image would be wider or taller than max_size
+        pub fn new(walls: &TextureRegistry, sprites: &[Texture], max_size: u32) -> Result<Atlas, AssetError> {
+            let textures: Vec<&Texture> = walls.iter().chain(sprites.iter()).collect();
+            let slots: Vec<(u32, u32)> = textures.iter().map(|t| (t.width + 2 * ATLAS_PADDING, t.height + 2 * ATLAS_PADDING)).collect();
+
+            // Rows about as wide as a square holding everything, and at least as wide as the widest texture
+            let area: u32 = slots.iter().map(|(w, h)| w * h).sum();
+            let width: u32 = ((area as f32).sqrt().ceil() as u32).max(slots.iter().map(|s| s.0).max().unwrap_or(0));
+            let mut order: Vec<usize> = (0..slots.len()).collect();
+            order.sort_by_key(|&i| std::cmp::Reverse(slots[i].1));
+
+            let mut at: Vec<(u32, u32)> = vec![(0, 0); slots.len()];
+            let (mut x, mut y, mut row): (u32, u32, u32) = (0, 0, 0);
+            for i in order {
+                if x + slots[i].0 > width { (x, y, row) = (0, y + row, 0); }
+                at[i] = (x, y);
+                x += slots[i].0;
+                row = row.max(slots[i].1);
+            }
+
+            let size: (u32, u32) = (width, y + row);
+            let max_size: u32 = max_size.min(u16::MAX as u32); // Image sizes are u16
+            if size.0 > max_size || size.1 > max_size { return Err(AssetError::AtlasSize(size, max_size)); }
+
+            let mut image: Image = Image::gen_image_color(width as u16, (y + row) as u16, Default::default());
+            let mut rects: Vec<Rect> = Vec::new();
+            for (t, &(x, y)) in textures.iter().zip(&at) {
+                let src: Image = t.image();
+                // Padding repeats the nearest texel
+                for dy in 0..t.height + 2 * ATLAS_PADDING {
+                    for dx in 0..t.width + 2 * ATLAS_PADDING {
+                        let sx: u32 = dx.saturating_sub(ATLAS_PADDING).min(t.width - 1);
+                        let sy: u32 = dy.saturating_sub(ATLAS_PADDING).min(t.height - 1);
+                        let from: usize = ((sy * t.width + sx) * 4) as usize;
+                        let to: usize = (((y + dy) * width + x + dx) * 4) as usize;
+                        image.bytes[to..to + 4].copy_from_slice(&src.bytes[from..from + 4]);
+                    }
+                }
+                rects.push(Rect::new((x + ATLAS_PADDING) as f32, (y + ATLAS_PADDING) as f32, t.width as f32, t.height as f32));
+            }
+
+            let sprites: Vec<Rect> = rects.split_off(walls.len());
+            Ok(Atlas { image: image, texture: Texture2D::empty(), walls: rects, sprites: sprites })
+        }
+
+        // Needs a window, the widest and tallest texture the GPU takes
+        pub fn max_size() -> u32 {
+            use macroquad::miniquad::gl::{glGetIntegerv, GL_MAX_TEXTURE_SIZE};
+            let mut size: i32 = 0;
+            // Only reads one integer into size
+            unsafe { glGetIntegerv(GL_MAX_TEXTURE_SIZE, &mut size); }
+            size.max(0) as u32
+        }
+
+        // Where the wall texture with this id is in the atlas
+        pub fn wall(&self, id: u32) -> Rect {
+            self.walls[(id - 1) as usize]
+        }
+
+        pub fn sprite(&self, i: usize) -> Rect {
+            self.sprites[i]
+        }
+
+        // Needs a window, creates the GPU copy of the image, replacing any earlier copy
+        pub fn upload(&mut self) {
+            self.unload();
+            self.texture = Texture2D::from_image(&self.image);
+        }
+
+        pub fn unload(&mut self) {
+            if self.uploaded() { self.texture.delete(); }
+            self.texture = Texture2D::empty();
+        }
+
+        pub fn uploaded(&self) -> bool {
+            self.texture != Texture2D::empty()
+        }
+    }
+}
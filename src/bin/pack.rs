@@ -0,0 +1,64 @@
+use raycast::Pack;
+
+/* 
+    Packs files into a single pack file the game can load assets from with --assets
+
+        cargo run --bin pack -- assets.pak src/assets
+
+    Directories are added with everything in them. Files are named by the path given here, so pack from
+    the same directory the level and manifest paths are relative to
+*/
+
+const USAGE: &str = "Usage: pack <out.pak> <file or directory>...";
+
+// Files under path, sorted so the same files always make the same pack
+fn collect(path: &std::path::Path, files: &mut Vec<std::path::PathBuf>) -> std::io::Result<()> {
+    if !path.is_dir() {
+        files.push(path.to_path_buf());
+        return Ok(());
+    }
+    let mut entries: Vec<std::path::PathBuf> = std::fs::read_dir(path)?.map(|e| e.map(|e| e.path())).collect::<Result<_, _>>()?;
+    entries.sort();
+    for entry in entries { collect(&entry, files)?; }
+    Ok(())
+}
+
+// Name the loader will ask for, forward slashes and no leading ./
+fn name(path: &std::path::Path) -> String {
+    let name: String = path.to_string_lossy().replace('\\', "/");
+    name.trim_start_matches("./").to_string()
+}
+
+fn pack(out: &str, inputs: &[String]) -> Result<usize, String> {
+    let mut paths: Vec<std::path::PathBuf> = Vec::new();
+    for input in inputs {
+        collect(std::path::Path::new(input), &mut paths).map_err(|e| format!("Failed to read {}: {}", input, e))?;
+    }
+
+    let mut files: Vec<(String, Vec<u8>)> = Vec::new();
+    for path in &paths {
+        let data: Vec<u8> = std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let name: String = name(path);
+        if files.iter().any(|(n, _)| *n == name) { continue; } // Given twice
+        if name.len() > u16::MAX as usize { return Err(format!("{} has too long a name to pack", name)); }
+        files.push((name, data));
+    }
+
+    std::fs::write(out, Pack::build(&files)).map_err(|e| format!("Failed to write {}: {}", out, e))?;
+    Ok(files.len())
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.len() < 2 || args.iter().any(|a| a == "-h" || a == "--help") {
+        eprintln!("{}", USAGE);
+        std::process::exit(2);
+    }
+    match pack(&args[0], &args[1..]) {
+        Ok(count) => println!("Packed {} files into {}", count, args[0]),
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+}
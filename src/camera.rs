@@ -0,0 +1,37 @@
+pub mod camera {
+    use macroquad::prelude::*;
+
+    /* 
+        Viewpoint the world is rendered from
+    */
+    #[derive(Clone, Copy)]
+    pub struct Camera {
+        pub pos: Vec2, // Position on the map
+        pub dir: Vec2, // Direction vector
+        pub plane: Vec2, // Camera plane
+        pub pitch: f32, // Cam pitch 
+        pub height: f32, // Cam vertical offset
+    }
+
+    impl Camera {
+        pub fn new(pos: Vec2, dir: Vec2) -> Camera {
+            Camera { 
+                pos: pos,
+                dir: dir,
+                plane: vec2(dir.y, -dir.x) * 0.66, // Perpendicular to dir
+                pitch: 0.0,
+                height: 0.0,
+            }
+        }
+
+        // Rotate dir and plane, positive r turns left
+        pub fn rotate(&mut self, r: f32) {
+            let old_dir_x: f32 = self.dir.x;
+            self.dir.x = self.dir.x * r.cos() - self.dir.y * r.sin();
+            self.dir.y = old_dir_x * r.sin() + self.dir.y * r.cos();
+            let old_plane_x: f32 = self.plane.x;
+            self.plane.x = self.plane.x * r.cos() - self.plane.y * r.sin();
+            self.plane.y = old_plane_x * r.sin() + self.plane.y * r.cos();
+        }
+    }
+}
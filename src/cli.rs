@@ -0,0 +1,91 @@
+pub mod cli {
+
+    /*
+        Command line options for the demo game
+    */
+    pub const USAGE: &str = "\
+Usage: raycast [options]
+
+Options:
+    --level <file>        level to load (default src/assets/levels/demo.map)
+    --assets <dir|pack>   directory or pack file the level and its images are read from (default .)
+    --size <WxH>          window size (default 640x480)
+    --fullscreen          start fullscreen
+    --resolution <WxH>    starting render resolution, scaled to the window size, overrides the settings file
+    --settings <file>     settings file to load and save (default settings.cfg)
+    --headless            render frames to images without opening a window
+    --frames <n>          frames to render in headless mode, turning a full circle from the spawn (default 60)
+    --out <dir>           directory headless frames are written to (default frames)
+    -h, --help            show this message";
+
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct Options {
+        pub level: String,
+        pub assets: String, // Directory or pack the level path is inside
+        pub size: (u32, u32), // Window size, also the size of headless frames like screenshots
+        pub fullscreen: bool,
+        pub resolution: Option<(u32, u32)>,
+        pub settings: String,
+        pub headless: bool,
+        pub frames: u32,
+        pub out: String,
+        pub help: bool,
+    }
+
+    impl Default for Options {
+        fn default() -> Options {
+            Options {
+                level: String::from("src/assets/levels/demo.map"),
+                assets: String::from("."),
+                size: (640, 480),
+                fullscreen: false,
+                resolution: None,
+                settings: String::from("settings.cfg"),
+                headless: false,
+                frames: 60,
+                out: String::from("frames"),
+                help: false,
+            }
+        }
+    }
+
+    impl Options {
+        // Arguments without the program name
+        pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Options, String> {
+            let mut options: Options = Options::default();
+            let mut args = args.into_iter();
+
+            while let Some(arg) = args.next() {
+                let mut value = |name: &str| args.next().ok_or(format!("{} needs a value", name));
+                match arg.as_str() {
+                    "--level" => options.level = value("--level")?,
+                    "--assets" => options.assets = value("--assets")?,
+                    "--size" => options.size = size("--size", &value("--size")?)?,
+                    "--fullscreen" => options.fullscreen = true,
+                    "--resolution" => options.resolution = Some(size("--resolution", &value("--resolution")?)?),
+                    "--settings" => options.settings = value("--settings")?,
+                    "--headless" => options.headless = true,
+                    "--frames" => {
+                        let frames: String = value("--frames")?;
+                        options.frames = match frames.parse() {
+                            Ok(n) if n > 0 => n,
+                            _ => return Err(format!("--frames expected a number above 0, got '{}'", frames)),
+                        };
+                    }
+                    "--out" => options.out = value("--out")?,
+                    "-h" | "--help" => options.help = true,
+                    _ => return Err(format!("unknown option '{}'", arg)),
+                }
+            }
+            Ok(options)
+        }
+    }
+
+    // <width>x<height>, both above 0
+    fn size(name: &str, value: &str) -> Result<(u32, u32), String> {
+        let parsed: Option<(u32, u32)> = value.split_once('x')
+            .and_then(|(w, h)| Some((w.parse().ok()?, h.parse().ok()?)))
+            .filter(|(w, h)| *w > 0 && *h > 0);
+        parsed.ok_or(format!("{} expected <width>x<height> like 640x480, got '{}'", name, value))
+    }
+}
@@ -4,20 +4,24 @@ pub mod display {
         hash, root_ui,
         widgets::{self},
     };
+    use crate::input::input::{Action, Controls, key_name};
 
     /* 
         Display settings, UI and Shaders
         Player settings aswell
     */
-    pub struct Settings {
+    pub struct Screen {
         pub render_target: RenderTarget,
-        pub crt_material: Material,
+        pub crt_material: Option<Material>, // None without shaders
         pub camera: Camera2D,
+    }
+    pub struct Settings {
         pub width: f32,
         pub height: f32,
         pub half_height: f32,
         pub half_width: f32,
         pub num_textures: usize,
+        pub threads: usize, // Worker threads for ray casting and floors
         // Settings
         pub settings: bool, 
         pub shaders: bool,   
@@ -36,29 +40,96 @@ pub mod display {
         pub resolution_x: f32,
         pub resolution_y: f32,
         pub move_speed: f32,
+        pub player_radius: f32, // Half the width of the square the player collides with walls as
         pub rot_speed: f32,    
         pub look_speed: f32,
+        pub mouse_look: bool,
+        pub mouse_sensitivity: f32,
+        pub gamepad_deadzone: f32, // How far a stick has to move before it does anything, 0 to 1
+        pub gamepad_sensitivity: f32, // Right stick look speed multiplier
         pub headbob: bool, 
         pub headbob_amount: f32,
         pub headbob_speed: f32,
     }
 
-    impl Settings {
-        pub fn new(num_textures: usize) -> Settings {
-            let render_target = render_target(640, 480); 
-            let crt_material = load_material(CRT_VERTEX_SHADER, CRT_FRAGMENT_SHADER, Default::default()).unwrap();
-            let mut camera = Camera2D::from_display_rect(Rect::new(0., 0., screen_width(), screen_height()));
+    // Everything saved to the settings file, the window size and texture count aren't
+    const SETTINGS: [&str; 27] = [
+        "threads", "shaders", "draw_sprites", "draw_walls", "draw_floors", "nightvision", "shadows", "dark_shading",
+        "sprite_shading_multiplier", "wall_shading_multiplier", "floor_shading_multiplier", "ceil_shading_multiplier",
+        "floor_texture", "ceil_texture", "resolution_x", "resolution_y", "move_speed", "player_radius", "rot_speed",
+        "look_speed", "mouse_look", "mouse_sensitivity", "gamepad_deadzone", "gamepad_sensitivity", "headbob",
+        "headbob_amount", "headbob_speed",
+    ];
+
+    fn boolean(value: &str) -> Result<bool, String> {
+        value.parse().map_err(|_| String::from("expected true or false"))
+    }
+
+    fn number<T>(value: &str, min: T, max: T) -> Result<T, String> 
+    where T: std::str::FromStr + PartialOrd + std::fmt::Display {
+        match value.parse::<T>() {
+            Ok(n) if n >= min && n <= max => Ok(n), // NaN fails both
+            _ => Err(format!("expected a number from {} to {}", min, max)),
+        }
+    }
+
+    // GPU side of the display, needs a window
+    impl Screen {
+        // Fails if the CRT shader doesn't compile, without_shaders still works then
+        pub fn new(ds: &Settings) -> Result<Screen, ShaderError> {
+            let mut screen: Screen = Screen::without_shaders(ds);
+            screen.crt_material = Some(load_material(CRT_VERTEX_SHADER, CRT_FRAGMENT_SHADER, Default::default())?);
+            Ok(screen)
+        }
+
+        pub fn without_shaders(ds: &Settings) -> Screen {
+            let render_target = render_target(ds.resolution_x as u32, ds.resolution_y as u32); 
+            let mut camera = Camera2D::from_display_rect(Rect::new(0., 0., ds.width, ds.height));
             camera.render_target = Some(render_target);
 
-            Settings { 
+            Screen { 
                 render_target: render_target, 
-                crt_material: crt_material,
+                crt_material: None,
                 camera: camera,
-                width: screen_width(),
-                height: screen_height(),
-                half_height: screen_height() / 2.0,
-                half_width: screen_width() / 2.0,
+            }
+        }
+
+        pub fn change_resolution(&mut self, resolution_x: f32, resolution_y: f32) {
+            self.render_target = render_target(resolution_x as u32, resolution_y as u32); 
+            self.camera.render_target = Some(self.render_target);
+        }
+
+        // Draw the render target to the window
+        pub fn draw(&self, shaders: bool) {
+            set_default_camera();
+            if let (true, Some(material)) = (shaders, self.crt_material) { 
+                gl_use_material(material); 
+            }
+            draw_texture_ex(
+                self.render_target.texture,
+                0.,
+                0.,
+                WHITE,
+                DrawTextureParams {
+                    dest_size: Some(vec2(screen_width(), screen_height())),
+                    flip_y: true,
+                    ..Default::default()
+                },
+            );
+            gl_use_default_material();
+        }
+    }
+
+    impl Settings {
+        pub fn new(width: f32, height: f32, num_textures: usize) -> Settings {
+            let last_texture: usize = num_textures.saturating_sub(1); // Levels can have fewer textures than the defaults
+            Settings { 
+                width: width,
+                height: height,
+                half_height: height / 2.0,
+                half_width: width / 2.0,
                 num_textures: num_textures,
+                threads: std::thread::available_parallelism().map_or(1, |n| n.get()),
                 // Settings
                 settings: false,
                 shaders: false,
@@ -72,25 +143,146 @@ pub mod display {
                 wall_shading_multiplier: 2.5,
                 floor_shading_multiplier: 0.08,
                 ceil_shading_multiplier: 0.1,
-                floor_texture: 1,
-                ceil_texture: 2,
+                floor_texture: last_texture.min(1),
+                ceil_texture: last_texture.min(2),
                 resolution_x: 640.0,
                 resolution_y: 480.0,
                 // Player settings
                 move_speed: 4.0,    
+                player_radius: 0.2,
                 rot_speed: 3.0,  
                 look_speed: 200.0,  
+                mouse_look: true,
+                mouse_sensitivity: 1.0,
+                gamepad_deadzone: 0.15,
+                gamepad_sensitivity: 1.0,
                 headbob: true, 
                 headbob_speed: 16.0,   
                 headbob_amount: 0.5,   
             }
         }
 
-        pub fn change_resolution(&mut self) {
-            self.render_target = render_target(self.resolution_x as u32, self.resolution_y as u32); 
-            self.camera.render_target = Some(self.render_target);
+        // Size of the view being drawn, in pixels
+        pub fn resize(&mut self, width: f32, height: f32) {
+            self.width = width;
+            self.height = height;
+            self.half_width = width / 2.0;
+            self.half_height = height / 2.0;
+        }
+
+        /*
+            Reads a settings config of <setting> = <value> lines on top of the current settings.
+            Unknown settings and values that don't parse or are out of range keep what was there and are returned as warnings
+        */
+        pub fn parse(&mut self, source: &str) -> Vec<String> {
+            let mut warnings: Vec<String> = Vec::new();
+            for (i, line) in source.lines().enumerate() {
+                let line: &str = line.split('#').next().unwrap().trim();
+                if line.is_empty() { continue; }
+
+                let (name, value): (&str, &str) = match line.split_once('=') {
+                    Some((name, value)) => (name.trim(), value.trim()),
+                    None => { warnings.push(format!("line {}: expected '<setting> = <value>'", i + 1)); continue; }
+                };
+                if !SETTINGS.contains(&name) {
+                    warnings.push(format!("line {}: unknown setting '{}'", i + 1, name));
+                    continue;
+                }
+                if let Err(e) = self.set(name, value) {
+                    warnings.push(format!("line {}: invalid {} '{}', {}", i + 1, name, value, e));
+                }
+            }
+            warnings
+        }
+
+        pub fn load(&mut self, path: &str) -> std::io::Result<Vec<String>> {
+            Ok(self.parse(&std::fs::read_to_string(path)?))
+        }
+
+        // Config file text for the current settings, parses back to the same settings
+        pub fn to_config(&self) -> String {
+            let mut config: String = String::from("# Settings, <setting> = <value>\n");
+            for name in SETTINGS {
+                config.push_str(&format!("{} = {}\n", name, self.get(name)));
+            }
+            config
+        }
+
+        pub fn save(&self, path: &str) -> std::io::Result<()> {
+            std::fs::write(path, self.to_config())
+        }
+
+        fn get(&self, name: &str) -> String {
+            match name {
+                "threads" => self.threads.to_string(),
+                "shaders" => self.shaders.to_string(),
+                "draw_sprites" => self.draw_sprites.to_string(),
+                "draw_walls" => self.draw_walls.to_string(),
+                "draw_floors" => self.draw_floors.to_string(),
+                "nightvision" => self.nightvision.to_string(),
+                "shadows" => self.shadows.to_string(),
+                "dark_shading" => self.dark_shading.to_string(),
+                "sprite_shading_multiplier" => self.sprite_shading_multiplier.to_string(),
+                "wall_shading_multiplier" => self.wall_shading_multiplier.to_string(),
+                "floor_shading_multiplier" => self.floor_shading_multiplier.to_string(),
+                "ceil_shading_multiplier" => self.ceil_shading_multiplier.to_string(),
+                "floor_texture" => self.floor_texture.to_string(),
+                "ceil_texture" => self.ceil_texture.to_string(),
+                "resolution_x" => self.resolution_x.to_string(),
+                "resolution_y" => self.resolution_y.to_string(),
+                "move_speed" => self.move_speed.to_string(),
+                "player_radius" => self.player_radius.to_string(),
+                "rot_speed" => self.rot_speed.to_string(),
+                "look_speed" => self.look_speed.to_string(),
+                "mouse_look" => self.mouse_look.to_string(),
+                "mouse_sensitivity" => self.mouse_sensitivity.to_string(),
+                "gamepad_deadzone" => self.gamepad_deadzone.to_string(),
+                "gamepad_sensitivity" => self.gamepad_sensitivity.to_string(),
+                "headbob" => self.headbob.to_string(),
+                "headbob_amount" => self.headbob_amount.to_string(),
+                "headbob_speed" => self.headbob_speed.to_string(),
+                _ => unreachable!("not a setting: {}", name),
+            }
+        }
+
+        // Ranges match the sliders in the settings window
+        fn set(&mut self, name: &str, value: &str) -> Result<(), String> {
+            let last_texture: usize = self.num_textures.saturating_sub(1);
+            match name {
+                "threads" => self.threads = number(value, 1, 64)?,
+                "shaders" => self.shaders = boolean(value)?,
+                "draw_sprites" => self.draw_sprites = boolean(value)?,
+                "draw_walls" => self.draw_walls = boolean(value)?,
+                "draw_floors" => self.draw_floors = boolean(value)?,
+                "nightvision" => self.nightvision = boolean(value)?,
+                "shadows" => self.shadows = boolean(value)?,
+                "dark_shading" => self.dark_shading = boolean(value)?,
+                "sprite_shading_multiplier" => self.sprite_shading_multiplier = number(value, 0.2, 5.0)?,
+                "wall_shading_multiplier" => self.wall_shading_multiplier = number(value, 1.0, 25.0)?,
+                "floor_shading_multiplier" => self.floor_shading_multiplier = number(value, 0.05, 1.0)?,
+                "ceil_shading_multiplier" => self.ceil_shading_multiplier = number(value, 0.05, 1.0)?,
+                "floor_texture" => self.floor_texture = number(value, 0, last_texture)?,
+                "ceil_texture" => self.ceil_texture = number(value, 0, last_texture)?,
+                "resolution_x" => self.resolution_x = number(value, 10.0, 4096.0)?, // Sliders stop at 640x480, the command line doesn't
+                "resolution_y" => self.resolution_y = number(value, 10.0, 4096.0)?,
+                "move_speed" => self.move_speed = number(value, 1.0, 10.0)?,
+                "player_radius" => self.player_radius = number(value, 0.05, 0.45)?,
+                "rot_speed" => self.rot_speed = number(value, 1.0, 10.0)?,
+                "look_speed" => self.look_speed = number(value, 100.0, 300.0)?,
+                "mouse_look" => self.mouse_look = boolean(value)?,
+                "mouse_sensitivity" => self.mouse_sensitivity = number(value, 0.1, 5.0)?,
+                "gamepad_deadzone" => self.gamepad_deadzone = number(value, 0.0, 0.5)?,
+                "gamepad_sensitivity" => self.gamepad_sensitivity = number(value, 0.1, 5.0)?,
+                "headbob" => self.headbob = boolean(value)?,
+                "headbob_amount" => self.headbob_amount = number(value, 0.3, 5.0)?,
+                "headbob_speed" => self.headbob_speed = number(value, 10.0, 30.0)?,
+                _ => unreachable!("not a setting: {}", name),
+            }
+            Ok(())
         }
-        pub fn draw_ui(&mut self) {
+
+        // The Controls section rebinds keys in controls
+        pub fn draw_ui(&mut self, screen: &mut Screen, controls: &mut Controls) {
             if self.settings {
                 draw_text(
                     format!("{} FPS", get_fps()).as_str(),
@@ -102,7 +294,7 @@ pub mod display {
 
                 widgets::Window::new(hash!(), vec2(20., 70.), vec2(400., 300.))
                     .label("Settings")
-                    .ui(&mut *root_ui(), |ui| {
+                    .ui(&mut root_ui(), |ui| {
                         ui.tree_node(hash!(), "Textures", |ui| {
                             ui.checkbox(hash!(), "Shaders",&mut self.shaders);
                             ui.separator();
@@ -144,17 +336,32 @@ pub mod display {
                             ui.label(None,"Resolution y");
                             ui.slider(hash!(), "", 10.0f32..480.0f32, &mut self.resolution_y);
                             if ui.button(None, format!("set target resolution: {}x{}", self.resolution_x as u32, self.resolution_y as u32)) {
-                                self.change_resolution();
+                                screen.change_resolution(self.resolution_x, self.resolution_y);
                             }
+                            ui.separator();
+                            ui.label(None, &format!("Render Threads: {}", self.threads));
+                            ui.same_line(0.0);
+                            if ui.button(None, "-") && self.threads > 1 { self.threads -= 1; }
+                            ui.same_line(0.0);
+                            if ui.button(None, "+") && self.threads < 64 { self.threads += 1; }
                         });           
                         ui.separator();
                         ui.tree_node(hash!(), "Player", |ui| {
                             ui.label(None,"Movement Speed");
                             ui.slider(hash!(), "[1.0 .. 10.0] ", 1.0f32..10.0f32, &mut self.move_speed);
+                            ui.label(None,"Player Radius");
+                            ui.slider(hash!(), "[0.05 .. 0.45] ", 0.05f32..0.45f32, &mut self.player_radius);
                             ui.label(None,"Rotate Speed");
                             ui.slider(hash!(), "[1.0 .. 10.0] ", 1.0f32..10.0f32, &mut self.rot_speed);
                             ui.label(None,"Look Speed");
                             ui.slider(hash!(), "[100.0 .. 300.0] ", 100.0f32..300.0f32, &mut self.look_speed);
+                            ui.checkbox(hash!(), "Mouse Look", &mut self.mouse_look);
+                            ui.label(None,"Mouse Sensitivity");
+                            ui.slider(hash!(), "[0.1 .. 5.0] ", 0.1f32..5.0f32, &mut self.mouse_sensitivity);
+                            ui.label(None,"Stick Deadzone");
+                            ui.slider(hash!(), "[0.0 .. 0.5] ", 0.0f32..0.5f32, &mut self.gamepad_deadzone);
+                            ui.label(None,"Stick Sensitivity");
+                            ui.slider(hash!(), "[0.1 .. 5.0] ", 0.1f32..5.0f32, &mut self.gamepad_sensitivity);
                             ui.separator();
                             ui.checkbox(hash!(), "Headbob",&mut self.headbob);
                             if self.headbob {
@@ -173,32 +380,31 @@ pub mod display {
                             ui.separator();
                         });     
                         ui.separator();             
+                        ui.tree_node(hash!(), "Controls", |ui| {
+                            for action in Action::ALL {
+                                let keys: Vec<String> = controls.keys(action).iter().map(|k| key_name(*k)).collect();
+                                let text: String = if controls.rebinding == Some(action) { String::from("press a key, Escape cancels") } 
+                                else { keys.join(", ") };
+                                ui.label(None, &format!("{}: {}", action.label(), text));
+                                ui.same_line(300.0);
+                                if ui.button(None, "Rebind") { controls.rebinding = Some(action); }
+                            }
+                            if ui.button(None, "Reset to defaults") {
+                                let defaults: Controls = Controls::default();
+                                for action in Action::ALL { controls.bind(action, defaults.keys(action).to_vec()); }
+                            }
+                        });
                     });
             }
 
-            set_default_camera();
-            if self.shaders { 
-                gl_use_material(self.crt_material); 
-            }
-            draw_texture_ex(
-                self.render_target.texture,
-                0.,
-                0.,
-                WHITE,
-                DrawTextureParams {
-                    dest_size: Some(vec2(screen_width(), screen_height())),
-                    flip_y: true,
-                    ..Default::default()
-                },
-            );
-            gl_use_default_material();
+            screen.draw(self.shaders);
         }
     }
 
 
     // Still learning how the hell this works 
     // Using example code for now 
-    const CRT_FRAGMENT_SHADER: &'static str = 
+    const CRT_FRAGMENT_SHADER: &str = 
     r#"#version 100
         precision lowp float;
 
@@ -248,7 +454,7 @@ pub mod display {
         }
     "#;
 
-    const CRT_VERTEX_SHADER: &'static str = 
+    const CRT_VERTEX_SHADER: &str = 
     "#version 100
         attribute vec3 position;
         attribute vec2 texcoord;
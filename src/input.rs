@@ -0,0 +1,392 @@
+pub mod input {
+    use macroquad::prelude::*;
+
+    /*
+        Rebindable controls
+        Every action the player can do is bound to one or more keys, loaded from a config file like
+
+            # <action> = <key>, <key> ...
+            forward = W, Up
+
+        Key names are the macroquad KeyCode names, W, Space, LeftShift, Key1 ...
+        A gamepad moves with the left stick, looks with the right stick and has fixed buttons
+    */
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    pub enum Action {
+        Forward,
+        Back,
+        StrafeLeft,
+        StrafeRight,
+        TurnLeft,
+        TurnRight,
+        LookUp,
+        LookDown,
+        Raise,
+        Lower,
+        Interact,
+        Settings,
+        Screenshot,
+        Quit,
+    }
+
+    impl Action {
+        pub const ALL: [Action; 14] = [
+            Action::Forward, Action::Back, Action::StrafeLeft, Action::StrafeRight, Action::TurnLeft, Action::TurnRight,
+            Action::LookUp, Action::LookDown, Action::Raise, Action::Lower, Action::Interact, Action::Settings,
+            Action::Screenshot, Action::Quit,
+        ];
+
+        // Name used in the config file
+        pub fn name(self) -> &'static str {
+            match self {
+                Action::Forward => "forward",
+                Action::Back => "back",
+                Action::StrafeLeft => "strafe_left",
+                Action::StrafeRight => "strafe_right",
+                Action::TurnLeft => "turn_left",
+                Action::TurnRight => "turn_right",
+                Action::LookUp => "look_up",
+                Action::LookDown => "look_down",
+                Action::Raise => "raise",
+                Action::Lower => "lower",
+                Action::Interact => "interact",
+                Action::Settings => "settings",
+                Action::Screenshot => "screenshot",
+                Action::Quit => "quit",
+            }
+        }
+
+        // Name shown in the settings window
+        pub fn label(self) -> &'static str {
+            match self {
+                Action::Forward => "Forward",
+                Action::Back => "Back",
+                Action::StrafeLeft => "Strafe Left",
+                Action::StrafeRight => "Strafe Right",
+                Action::TurnLeft => "Turn Left",
+                Action::TurnRight => "Turn Right",
+                Action::LookUp => "Look Up",
+                Action::LookDown => "Look Down",
+                Action::Raise => "Raise Camera",
+                Action::Lower => "Lower Camera",
+                Action::Interact => "Interact",
+                Action::Settings => "Settings",
+                Action::Screenshot => "Screenshot",
+                Action::Quit => "Quit",
+            }
+        }
+
+        fn default_keys(self) -> Vec<KeyCode> {
+            match self {
+                Action::Forward => vec![KeyCode::W, KeyCode::Up],
+                Action::Back => vec![KeyCode::S, KeyCode::Down],
+                Action::StrafeLeft => vec![KeyCode::A],
+                Action::StrafeRight => vec![KeyCode::D],
+                Action::TurnLeft => vec![KeyCode::Left],
+                Action::TurnRight => vec![KeyCode::Right],
+                Action::LookUp => vec![KeyCode::Q],
+                Action::LookDown => vec![KeyCode::E],
+                Action::Raise => vec![KeyCode::Z],
+                Action::Lower => vec![KeyCode::X],
+                Action::Interact => vec![KeyCode::F],
+                Action::Settings => vec![KeyCode::Tab],
+                Action::Screenshot => vec![KeyCode::P],
+                Action::Quit => vec![KeyCode::Escape],
+            }
+        }
+    }
+
+    // Every key that can be bound, named by their Debug names
+    const KEYS: [KeyCode; 120] = [
+        KeyCode::Space, KeyCode::Apostrophe, KeyCode::Comma, KeyCode::Minus, KeyCode::Period, KeyCode::Slash,
+        KeyCode::Key0, KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4, KeyCode::Key5, KeyCode::Key6,
+        KeyCode::Key7, KeyCode::Key8, KeyCode::Key9, KeyCode::Semicolon, KeyCode::Equal,
+        KeyCode::A, KeyCode::B, KeyCode::C, KeyCode::D, KeyCode::E, KeyCode::F, KeyCode::G, KeyCode::H, KeyCode::I,
+        KeyCode::J, KeyCode::K, KeyCode::L, KeyCode::M, KeyCode::N, KeyCode::O, KeyCode::P, KeyCode::Q, KeyCode::R,
+        KeyCode::S, KeyCode::T, KeyCode::U, KeyCode::V, KeyCode::W, KeyCode::X, KeyCode::Y, KeyCode::Z,
+        KeyCode::LeftBracket, KeyCode::Backslash, KeyCode::RightBracket, KeyCode::GraveAccent, KeyCode::World1,
+        KeyCode::World2, KeyCode::Escape, KeyCode::Enter, KeyCode::Tab, KeyCode::Backspace, KeyCode::Insert,
+        KeyCode::Delete, KeyCode::Right, KeyCode::Left, KeyCode::Down, KeyCode::Up, KeyCode::PageUp,
+        KeyCode::PageDown, KeyCode::Home, KeyCode::End, KeyCode::CapsLock, KeyCode::ScrollLock, KeyCode::NumLock,
+        KeyCode::PrintScreen, KeyCode::Pause,
+        KeyCode::F1, KeyCode::F2, KeyCode::F3, KeyCode::F4, KeyCode::F5, KeyCode::F6, KeyCode::F7, KeyCode::F8,
+        KeyCode::F9, KeyCode::F10, KeyCode::F11, KeyCode::F12, KeyCode::F13, KeyCode::F14, KeyCode::F15,
+        KeyCode::F16, KeyCode::F17, KeyCode::F18, KeyCode::F19, KeyCode::F20, KeyCode::F21, KeyCode::F22,
+        KeyCode::F23, KeyCode::F24, KeyCode::F25,
+        KeyCode::Kp0, KeyCode::Kp1, KeyCode::Kp2, KeyCode::Kp3, KeyCode::Kp4, KeyCode::Kp5, KeyCode::Kp6,
+        KeyCode::Kp7, KeyCode::Kp8, KeyCode::Kp9, KeyCode::KpDecimal, KeyCode::KpDivide, KeyCode::KpMultiply,
+        KeyCode::KpSubtract, KeyCode::KpAdd, KeyCode::KpEnter, KeyCode::KpEqual,
+        KeyCode::LeftShift, KeyCode::LeftControl, KeyCode::LeftAlt, KeyCode::LeftSuper, KeyCode::RightShift,
+        KeyCode::RightControl, KeyCode::RightAlt, KeyCode::RightSuper, KeyCode::Menu,
+    ];
+
+    pub fn key_name(key: KeyCode) -> String {
+        format!("{:?}", key)
+    }
+
+    // Case doesn't matter, w and W are the same key
+    pub fn parse_key(name: &str) -> Option<KeyCode> {
+        KEYS.iter().copied().find(|k| key_name(*k).eq_ignore_ascii_case(name))
+    }
+
+    /*
+        Radial deadzone, sticks rarely rest at exactly zero.
+        Anything inside the deadzone is zero and the rest is rescaled so the stick still goes from 0 to 1
+    */
+    pub fn deadzone(stick: Vec2, deadzone: f32) -> Vec2 {
+        let length: f32 = stick.length();
+        if length <= deadzone || deadzone >= 1.0 { return Vec2::ZERO; }
+        stick / length * ((length - deadzone) / (1.0 - deadzone)).min(1.0)
+    }
+
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    pub enum Stick {
+        Left,
+        Right,
+    }
+
+    pub use gamepad::Gamepad;
+
+    /*
+        Gamepads through gilrs, the last pad that was touched is the one being read
+    */
+    #[cfg(feature = "gamepad")]
+    mod gamepad {
+        use macroquad::prelude::*;
+        use gilrs::{Gilrs, GamepadId, Button, Axis, EventType};
+        use super::{Action, Stick};
+
+        #[derive(Default)]
+        pub struct Gamepad {
+            gilrs: Option<Gilrs>, // None when there's no gamepad support
+            active: Option<GamepadId>,
+            pressed: Vec<Button>, // Buttons pressed since the last update
+        }
+
+        // Gamepad button for the action, sticks cover moving and looking
+        fn button(action: Action) -> Option<Button> {
+            match action {
+                Action::Interact => Some(Button::South),
+                Action::Settings => Some(Button::Start),
+                Action::Raise => Some(Button::RightTrigger2),
+                Action::Lower => Some(Button::LeftTrigger2),
+                _ => None,
+            }
+        }
+
+        impl Gamepad {
+            // Fails when the platform has no gamepad support, the error says why
+            pub fn new() -> Result<Gamepad, String> {
+                let gilrs: Gilrs = Gilrs::new().map_err(|e| e.to_string())?;
+                Ok(Gamepad { gilrs: Some(gilrs), active: None, pressed: Vec::new() })
+            }
+
+            pub fn update(&mut self) {
+                self.pressed.clear();
+                let gilrs: &mut Gilrs = match &mut self.gilrs {
+                    Some(gilrs) => gilrs,
+                    None => return,
+                };
+                while let Some(event) = gilrs.next_event() {
+                    match event.event {
+                        EventType::Disconnected if self.active == Some(event.id) => { self.active = None; continue; }
+                        EventType::Disconnected => continue,
+                        EventType::ButtonPressed(button, _) => self.pressed.push(button),
+                        _ => (),
+                    }
+                    self.active = Some(event.id);
+                }
+            }
+
+            // Whether the button for an action is held
+            pub fn down(&self, action: Action) -> bool {
+                match (&self.gilrs, self.active, button(action)) {
+                    (Some(gilrs), Some(id), Some(button)) => gilrs.connected_gamepad(id).is_some_and(|pad| pad.is_pressed(button)),
+                    _ => false,
+                }
+            }
+
+            pub fn pressed(&self, action: Action) -> bool {
+                button(action).is_some_and(|b| self.pressed.contains(&b))
+            }
+
+            // Raw stick position, up and right are positive
+            pub fn stick(&self, stick: Stick) -> Vec2 {
+                let (x, y): (Axis, Axis) = match stick {
+                    Stick::Left => (Axis::LeftStickX, Axis::LeftStickY),
+                    Stick::Right => (Axis::RightStickX, Axis::RightStickY),
+                };
+                match (&self.gilrs, self.active) {
+                    (Some(gilrs), Some(id)) => gilrs.connected_gamepad(id).map_or(Vec2::ZERO, |pad| vec2(pad.value(x), pad.value(y))),
+                    _ => Vec2::ZERO,
+                }
+            }
+        }
+    }
+
+    /*
+        Built without the gamepad feature, there is never a pad to read
+    */
+    #[cfg(not(feature = "gamepad"))]
+    mod gamepad {
+        use macroquad::prelude::*;
+        use super::{Action, Stick};
+
+        #[derive(Default)]
+        pub struct Gamepad {}
+
+        impl Gamepad {
+            pub fn new() -> Result<Gamepad, String> {
+                Err(String::from("built without the gamepad feature"))
+            }
+
+            pub fn update(&mut self) {}
+
+            pub fn down(&self, _action: Action) -> bool {
+                false
+            }
+
+            pub fn pressed(&self, _action: Action) -> bool {
+                false
+            }
+
+            pub fn stick(&self, _stick: Stick) -> Vec2 {
+                Vec2::ZERO
+            }
+        }
+    }
+
+    pub struct Controls {
+        keys: Vec<Vec<KeyCode>>, // Indexed by Action
+        pub rebinding: Option<Action>, // Waiting for a key to bind to this action, all input is ignored meanwhile
+        changed: bool, // Rebound since last asked, needs saving
+        swallowed: bool, // A key finished rebinding this frame, so it isn't also input
+        pub gamepad: Gamepad,
+    }
+
+    impl Default for Controls {
+        fn default() -> Controls {
+            Controls {
+                keys: Action::ALL.iter().map(|a| a.default_keys()).collect(),
+                rebinding: None,
+                changed: false,
+                swallowed: false,
+                gamepad: Gamepad::default(),
+            }
+        }
+    }
+
+    impl Controls {
+        /*
+            Reads a controls config, actions missing from it keep their default keys.
+            Lines that can't be understood are skipped and returned as warnings
+        */
+        pub fn parse(source: &str) -> (Controls, Vec<String>) {
+            let mut controls: Controls = Controls::default();
+            let mut warnings: Vec<String> = Vec::new();
+
+            for (i, line) in source.lines().enumerate() {
+                let line: &str = line.split('#').next().unwrap().trim();
+                if line.is_empty() { continue; }
+
+                let (name, keys): (&str, &str) = match line.split_once('=') {
+                    Some((name, keys)) => (name.trim(), keys),
+                    None => { warnings.push(format!("line {}: expected '<action> = <key>, ...'", i + 1)); continue; }
+                };
+                let action: Action = match Action::ALL.iter().find(|a| a.name() == name) {
+                    Some(a) => *a,
+                    None => { warnings.push(format!("line {}: unknown action '{}'", i + 1, name)); continue; }
+                };
+
+                let mut bound: Vec<KeyCode> = Vec::new();
+                for key in keys.split(|c: char| c == ',' || c.is_whitespace()).filter(|k| !k.is_empty()) {
+                    match parse_key(key) {
+                        Some(k) => bound.push(k),
+                        None => warnings.push(format!("line {}: unknown key '{}'", i + 1, key)),
+                    }
+                }
+                controls.keys[action as usize] = bound;
+            }
+            (controls, warnings)
+        }
+
+        pub fn load(path: &str) -> std::io::Result<(Controls, Vec<String>)> {
+            Ok(Controls::parse(&std::fs::read_to_string(path)?))
+        }
+
+        // Config file text for the current bindings, parses back to the same controls
+        pub fn to_config(&self) -> String {
+            let mut config: String = String::from("# Controls, <action> = <key>, <key> ...\n# Key names are macroquad KeyCode names like W, Space, LeftShift, Key1, Up\n");
+            for action in Action::ALL {
+                let keys: Vec<String> = self.keys(action).iter().map(|k| key_name(*k)).collect();
+                config.push_str(&format!("{} = {}\n", action.name(), keys.join(", ")));
+            }
+            config
+        }
+
+        pub fn save(&self, path: &str) -> std::io::Result<()> {
+            std::fs::write(path, self.to_config())
+        }
+
+        pub fn keys(&self, action: Action) -> &[KeyCode] {
+            &self.keys[action as usize]
+        }
+
+        pub fn bind(&mut self, action: Action, keys: Vec<KeyCode>) {
+            self.keys[action as usize] = keys;
+            self.changed = true;
+        }
+
+        // True once after the bindings change
+        pub fn take_changed(&mut self) -> bool {
+            std::mem::replace(&mut self.changed, false)
+        }
+
+        pub fn down(&self, action: Action) -> bool {
+            self.listening() && (self.keys(action).iter().any(|k| is_key_down(*k)) 
+                || self.gamepad.down(action))
+        }
+
+        pub fn pressed(&self, action: Action) -> bool {
+            self.listening() && (self.keys(action).iter().any(|k| is_key_pressed(*k)) 
+                || self.gamepad.pressed(action))
+        }
+
+        // -1 while only negative is held, 1 while only positive is held
+        pub fn axis(&self, negative: Action, positive: Action) -> f32 {
+            self.down(positive) as i32 as f32 - self.down(negative) as i32 as f32
+        }
+
+        // Stick position with the deadzone taken out, zero while rebinding like the keys
+        pub fn stick(&self, stick: Stick, deadzone_size: f32) -> Vec2 {
+            if !self.listening() { return Vec2::ZERO; }
+            deadzone(self.gamepad.stick(stick), deadzone_size)
+        }
+
+        // Once a frame, reads the gamepad and binds the next key pressed to the action being rebound
+        pub fn update(&mut self) {
+            self.gamepad.update();
+            self.rebind(get_last_key_pressed());
+        }
+
+        /*
+            Binds the key pressed this frame to the action being rebound, Escape cancels. Returns whether the key
+            was used up that way, actions ignore input for the rest of the frame so Escape doesn't also quit
+            and the new key doesn't fire straight away
+        */
+        pub fn rebind(&mut self, key: Option<KeyCode>) -> bool {
+            self.swallowed = false;
+            if let (Some(action), Some(key)) = (self.rebinding, key) {
+                if key != KeyCode::Escape { self.bind(action, vec![key]); }
+                self.rebinding = None;
+                self.swallowed = true;
+            }
+            self.swallowed
+        }
+
+        // Whether actions read input, not while rebinding or in the frame a rebind finished
+        fn listening(&self) -> bool {
+            self.rebinding.is_none() && !self.swallowed
+        }
+    }
+}
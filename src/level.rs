@@ -0,0 +1,434 @@
+pub mod level {
+    use std::collections::{BTreeMap, HashMap};
+    use std::fmt;
+    use macroquad::prelude::*;
+    use crate::map::world::{Entity, AssetError};
+
+    /*
+        Level file parsing
+        A level is a plain text file split into [sections], see src/assets/levels/demo.map
+        Anything after a # is a comment
+    */
+    // Name of an asset in the manifest, along with where the level file used it
+    pub struct AssetRef {
+        pub name: String,
+        pub line: usize,
+        pub column: usize,
+    }
+
+    impl AssetRef {
+        pub fn error(&self, message: String) -> LevelError {
+            LevelError::Parse { line: self.line, column: self.column, message: message }
+        }
+    }
+
+    pub struct SpriteDef {
+        pub name: AssetRef,
+        pub x: f32,
+        pub y: f32,
+    }
+    pub struct Spawn {
+        pub pos: Vec2,
+        pub dir: Vec2,
+    }
+    pub struct Level {
+        pub world_map: Vec<u32>,
+        pub floor_map: Option<Vec<u32>>,
+        pub ceil_map: Option<Vec<u32>>,
+        pub height_map: Option<Vec<f32>>,
+        pub width: usize,
+        pub height: usize,
+        pub outside: u32,
+        pub manifest: String, // Path of the asset manifest the names are looked up in
+        pub sky: Option<AssetRef>,
+        pub textures: Vec<AssetRef>, // Indexed by texture id - 1
+        pub sprites: Vec<SpriteDef>,
+        pub entities: Vec<Entity>,
+        pub spawn: Spawn,
+    }
+
+    #[derive(Debug)]
+    pub enum LevelError {
+        File(String, std::io::Error),
+        Parse { line: usize, column: usize, message: String },
+        Missing(&'static str),
+        NoManifest,
+        Manifest(String, Box<LevelError>), // Error in the manifest file at the path
+        Asset(AssetError), // A texture or sprite image the level uses
+    }
+
+    impl fmt::Display for LevelError {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            match self {
+                LevelError::File(path, e) => write!(f, "couldn't read {}: {}", path, e),
+                LevelError::Parse { line, column, message } => write!(f, "line {}, column {}: {}", line, column, message),
+                LevelError::Missing(section) => write!(f, "missing [{}] section", section),
+                LevelError::NoManifest => write!(f, "missing 'manifest <path>' in [level]"),
+                LevelError::Manifest(path, e) => write!(f, "{}: {}", path, e),
+                LevelError::Asset(e) => write!(f, "{}", e),
+            }
+        }
+    }
+
+    impl std::error::Error for LevelError {}
+
+    #[derive(Clone, Copy, PartialEq)]
+    enum Section { None, Level, Textures, Sprites, Entities, Spawn, Map, Floor, Ceiling, Heights }
+
+    // A word on a line along with its 1-based line and column
+    #[derive(Clone)]
+    struct Token<'a> {
+        text: &'a str,
+        line: usize,
+        column: usize,
+    }
+
+    impl<'a> Token<'a> {
+        fn error(&self, message: String) -> LevelError {
+            LevelError::Parse { line: self.line, column: self.column, message: message }
+        }
+
+        fn parse<T: std::str::FromStr>(&self, what: &str) -> Result<T, LevelError> {
+            self.text.parse::<T>().map_err(|_| self.error(format!("expected {}, found '{}'", what, self.text)))
+        }
+
+        // f32 parses nan and inf, positions have to be real numbers
+        fn number(&self) -> Result<f32, LevelError> {
+            let n: f32 = self.parse("a number")?;
+            if !n.is_finite() { return Err(self.error(format!("expected a finite number, found '{}'", self.text))); }
+            Ok(n)
+        }
+
+        fn asset(&self) -> AssetRef {
+            AssetRef { name: self.text.to_string(), line: self.line, column: self.column }
+        }
+    }
+
+    // Rows of a [map], [floor], [ceiling] or [heights] section
+    struct Grid<'a> {
+        header: Token<'a>,
+        rows: Vec<Vec<Token<'a>>>,
+    }
+
+    impl<'a> Grid<'a> {
+        fn push(&mut self, tokens: Vec<Token<'a>>) -> Result<(), LevelError> {
+            if let Some(row) = self.rows.first() {
+                if tokens.len() != row.len() {
+                    let at = if tokens.len() > row.len() { &tokens[row.len()] } else { &tokens[tokens.len() - 1] };
+                    return Err(at.error(format!("expected {} cells in this row, found {}", row.len(), tokens.len())));
+                }
+            }
+            self.rows.push(tokens);
+            Ok(())
+        }
+
+        fn check_size(&self, width: usize, height: usize) -> Result<(), LevelError> {
+            if self.rows.len() != height || self.rows[0].len() != width {
+                let found: (usize, usize) = (self.rows.first().map_or(0, |r| r.len()), self.rows.len());
+                return Err(self.header.error(format!("expected {}x{} cells like the [map], found {}x{}", width, height, found.0, found.1)));
+            }
+            Ok(())
+        }
+
+        // Texture ids row by row, the grid has to be width by height
+        fn ids(&self, width: usize, height: usize, num_textures: u32) -> Result<Vec<u32>, LevelError> {
+            self.check_size(width, height)?;
+            let mut ids: Vec<u32> = Vec::with_capacity(width * height);
+            for token in self.rows.iter().flatten() {
+                let id: u32 = token.parse("a texture id")?;
+                if id > num_textures { return Err(token.error(format!("texture {} is not defined in [textures]", id))); }
+                ids.push(id);
+            }
+            Ok(ids)
+        }
+
+        // Wall heights row by row, 1.0 is a normal wall
+        fn heights(&self, width: usize, height: usize) -> Result<Vec<f32>, LevelError> {
+            self.check_size(width, height)?;
+            let mut heights: Vec<f32> = Vec::with_capacity(width * height);
+            for token in self.rows.iter().flatten() {
+                let h: f32 = token.parse("a wall height")?;
+                if !(h >= 0.0 && h.is_finite()) { return Err(token.error(format!("expected a wall height of 0 or more, found '{}'", token.text))); }
+                heights.push(h);
+            }
+            Ok(heights)
+        }
+    }
+
+    fn tokenize(line: &str, line_number: usize) -> Vec<Token<'_>> {
+        let line = match line.find('#') {
+            Some(i) => &line[..i],
+            None => line,
+        };
+        let mut tokens: Vec<Token> = Vec::new();
+        let mut start: Option<usize> = None;
+
+        for (i, c) in line.char_indices().chain(std::iter::once((line.len(), ' '))) {
+            let separator = c.is_whitespace() || c == ',';
+            match start {
+                Some(s) if separator => {
+                    tokens.push(Token { text: &line[s..i], line: line_number, column: line[..s].chars().count() + 1 });
+                    start = None;
+                }
+                None if !separator => start = Some(i),
+                _ => (),
+            }
+        }
+        tokens
+    }
+
+    // Checks a line has exactly the expected number of words
+    fn expect_len(tokens: &[Token], len: usize, usage: &str) -> Result<(), LevelError> {
+        if tokens.len() < len {
+            let last = &tokens[tokens.len() - 1];
+            return Err(LevelError::Parse {
+                line: last.line,
+                column: last.column + last.text.chars().count(),
+                message: format!("expected '{}'", usage)
+            });
+        }
+        if tokens.len() > len {
+            return Err(tokens[len].error(format!("unexpected '{}', expected '{}'", tokens[len].text, usage)));
+        }
+        Ok(())
+    }
+
+    impl Level {
+        pub fn parse(source: &str) -> Result<Level, LevelError> {
+            let mut section = Section::None;
+            let mut textures_line: usize = 0;
+            let mut textures: BTreeMap<u32, AssetRef> = BTreeMap::new();
+            let mut sprites: Vec<SpriteDef> = Vec::new();
+            // Entities and the spawn are checked against the map once it has been read
+            let mut entities: Vec<(Entity, Token, Token)> = Vec::new();
+            let mut powers: Vec<(usize, Token, Token)> = Vec::new(); // Entity index and its texture names
+            let mut pos: Option<(Vec2, Token)> = None;
+            let mut dir: Vec2 = vec2(-1.0, 0.0);
+            let mut outside: Option<(u32, Token)> = None;
+            let mut manifest: Option<String> = None;
+            let mut sky: Option<AssetRef> = None;
+            let mut map: Option<Grid> = None;
+            let mut floor: Option<Grid> = None;
+            let mut ceiling: Option<Grid> = None;
+            let mut heights: Option<Grid> = None;
+
+            for (i, line) in source.lines().enumerate() {
+                let tokens = tokenize(line, i + 1);
+                if tokens.is_empty() { continue; }
+
+                let first = &tokens[0];
+                if first.text.starts_with('[') {
+                    section = match first.text {
+                        "[level]" => Section::Level,
+                        "[textures]" => Section::Textures,
+                        "[sprites]" => Section::Sprites,
+                        "[entities]" => Section::Entities,
+                        "[spawn]" => Section::Spawn,
+                        "[map]" => Section::Map,
+                        "[floor]" => Section::Floor,
+                        "[ceiling]" => Section::Ceiling,
+                        "[heights]" => Section::Heights,
+                        _ => return Err(first.error(format!("unknown section '{}'", first.text))),
+                    };
+                    if section == Section::Textures { textures_line = first.line; }
+                    expect_len(&tokens, 1, first.text)?;
+
+                    let grid: &mut Option<Grid> = match section {
+                        Section::Map => &mut map,
+                        Section::Floor => &mut floor,
+                        Section::Ceiling => &mut ceiling,
+                        Section::Heights => &mut heights,
+                        _ => continue,
+                    };
+                    if grid.is_some() { return Err(first.error(format!("{} is defined twice", first.text))); }
+                    *grid = Some(Grid { header: tokens.into_iter().next().unwrap(), rows: Vec::new() });
+                    continue;
+                }
+
+                match section {
+                    Section::None => return Err(first.error(String::from("expected a [section] header"))),
+
+                    Section::Level => {
+                        match first.text {
+                            "outside" => {
+                                expect_len(&tokens, 2, "outside <id>")?;
+                                let id: u32 = tokens[1].parse("a texture id")?;
+                                outside = Some((id, tokens.into_iter().nth(1).unwrap()));
+                            }
+                            "manifest" => {
+                                expect_len(&tokens, 2, "manifest <path>")?;
+                                manifest = Some(tokens[1].text.to_string());
+                            }
+                            "sky" => {
+                                expect_len(&tokens, 2, "sky <name>")?;
+                                sky = Some(tokens[1].asset());
+                            }
+                            _ => return Err(first.error(format!("unknown level key '{}'", first.text))),
+                        }
+                    }
+
+                    Section::Textures => {
+                        expect_len(&tokens, 2, "<id> <name>")?;
+                        let id: u32 = first.parse("a texture id")?;
+                        if id == 0 { return Err(first.error(String::from("texture id 0 is reserved for empty cells"))); }
+                        if textures.insert(id, tokens[1].asset()).is_some() {
+                            return Err(first.error(format!("texture {} is defined twice", id)));
+                        }
+                    }
+
+                    Section::Sprites => {
+                        expect_len(&tokens, 3, "<name> <x> <y>")?;
+                        sprites.push(SpriteDef {
+                            name: first.asset(),
+                            x: tokens[1].number()?,
+                            y: tokens[2].number()?,
+                        });
+                    }
+
+                    Section::Entities => {
+                        match first.text {
+                            "door" => {
+                                expect_len(&tokens, 3, "door <row> <col>")?;
+                                let coords: (i32, i32) = (tokens[1].parse("a row")?, tokens[2].parse("a column")?);
+                                let mut tokens = tokens.into_iter();
+                                entities.push((Entity::Door(coords), tokens.next().unwrap(), tokens.next().unwrap()));
+                            }
+                            "power" => {
+                                expect_len(&tokens, 5, "power <on> <off> <row> <col>")?;
+                                let coords: (i32, i32) = (tokens[3].parse("a row")?, tokens[4].parse("a column")?);
+                                let mut tokens = tokens.into_iter();
+                                let (on, off): (Token, Token) = (tokens.nth(1).unwrap(), tokens.next().unwrap());
+                                // Texture ids are filled in once every [textures] name is known
+                                entities.push((Entity::Power((0, 0), coords), on.clone(), tokens.next().unwrap()));
+                                powers.push((entities.len() - 1, on, off));
+                            }
+                            "pushwall" => {
+                                expect_len(&tokens, 4, "pushwall <row> <col> <cells>")?;
+                                let coords: (i32, i32) = (tokens[1].parse("a row")?, tokens[2].parse("a column")?);
+                                let cells: u32 = tokens[3].parse("a number of cells")?;
+                                if cells == 0 { return Err(tokens[3].error(String::from("a push wall has to move at least 1 cell"))); }
+                                let mut tokens = tokens.into_iter();
+                                entities.push((Entity::PushWall(coords, cells), tokens.next().unwrap(), tokens.next().unwrap()));
+                            }
+                            _ => return Err(first.error(format!("unknown entity '{}'", first.text))),
+                        }
+                    }
+
+                    Section::Spawn => {
+                        let usage = match first.text {
+                            "pos" => "pos <x> <y>",
+                            "dir" => "dir <x> <y>",
+                            _ => return Err(first.error(format!("unknown spawn key '{}'", first.text))),
+                        };
+                        expect_len(&tokens, 3, usage)?;
+                        let v: Vec2 = vec2(tokens[1].number()?, tokens[2].number()?);
+                        if first.text == "pos" { pos = Some((v, tokens.into_iter().nth(1).unwrap())); }
+                        else if v.length() == 0.0 { return Err(tokens[1].error(String::from("spawn direction can't be zero"))); }
+                        else { dir = v.normalize(); }
+                    }
+
+                    Section::Map => map.as_mut().unwrap().push(tokens)?,
+                    Section::Floor => floor.as_mut().unwrap().push(tokens)?,
+                    Section::Ceiling => ceiling.as_mut().unwrap().push(tokens)?,
+                    Section::Heights => heights.as_mut().unwrap().push(tokens)?,
+                }
+            }
+
+            let map: Grid = match map {
+                Some(m) if !m.rows.is_empty() => m,
+                _ => return Err(LevelError::Missing("map")),
+            };
+
+            // Texture ids have to run from 1 with no gaps, they index into World::textures
+            for (i, id) in textures.keys().enumerate() {
+                if *id != i as u32 + 1 {
+                    return Err(LevelError::Parse {
+                        line: textures_line,
+                        column: 1,
+                        message: format!("texture ids must start at 1 with no gaps, {} is missing", i + 1)
+                    });
+                }
+            }
+            let num_textures = textures.len() as u32;
+
+            // Every name has one id, so entities naming a texture get the same one the map uses
+            let mut ids: HashMap<&str, u32> = HashMap::new();
+            for (id, texture) in &textures {
+                if let Some(other) = ids.insert(&texture.name, *id) {
+                    return Err(texture.error(format!("'{}' is already texture {}", texture.name, other)));
+                }
+            }
+            let id = |name: &Token| -> Result<u32, LevelError> {
+                ids.get(name.text).copied().ok_or_else(|| name.error(format!("'{}' is not in [textures]", name.text)))
+            };
+            for (i, on, off) in &powers {
+                if let Entity::Power(texture, _) = &mut entities[*i].0 { *texture = (id(on)?, id(off)?); }
+            }
+
+            let width = map.rows[0].len();
+            let height = map.rows.len();
+            let world_map: Vec<u32> = map.ids(width, height, num_textures)?;
+            let floor_map: Option<Vec<u32>> = match floor {
+                Some(g) => Some(g.ids(width, height, num_textures)?),
+                None => None,
+            };
+            let ceil_map: Option<Vec<u32>> = match ceiling {
+                Some(g) => Some(g.ids(width, height, num_textures)?),
+                None => None,
+            };
+
+            let height_map: Option<Vec<f32>> = match heights {
+                Some(g) => Some(g.heights(width, height)?),
+                None => None,
+            };
+
+            let (pos, pos_token): (Vec2, Token) = match pos {
+                Some(p) => p,
+                None => return Err(LevelError::Missing("spawn")),
+            };
+
+            let outside: u32 = match outside {
+                Some((id, token)) if id > num_textures => return Err(token.error(format!("texture {} is not defined in [textures]", id))),
+                Some((id, _)) => id,
+                None => 0,
+            };
+
+            let in_map = |r: i32, c: i32| r >= 0 && c >= 0 && (r as usize) < height && (c as usize) < width;
+            // For doors and push walls the first token is the entity name, their texture is the one in the map
+            for (entity, texture, coords) in &entities {
+                let c: (i32, i32) = match *entity {
+                    Entity::Door(c) | Entity::PushWall(c, _) | Entity::Power(_, c) => c,
+                };
+                if !in_map(c.0, c.1) { return Err(coords.error(format!("{:?} is outside the map", c))); }
+                let name: &str = match entity {
+                    Entity::Door(_) => "door",
+                    Entity::PushWall(..) => "push wall",
+                    Entity::Power(..) => continue,
+                };
+                if world_map[width * c.0 as usize + c.1 as usize] == 0 {
+                    return Err(texture.error(format!("{} at {:?} needs a texture id in the map", name, c)));
+                }
+            }
+
+            if !in_map(pos.x.floor() as i32, pos.y.floor() as i32) || world_map[width * pos.x as usize + pos.y as usize] != 0 {
+                return Err(pos_token.error(format!("spawn ({}, {}) is not an empty map cell", pos.x, pos.y)));
+            }
+
+            Ok(Level {
+                world_map: world_map,
+                floor_map: floor_map,
+                ceil_map: ceil_map,
+                height_map: height_map,
+                width: width,
+                height: height,
+                outside: outside,
+                manifest: manifest.ok_or(LevelError::NoManifest)?,
+                sky: sky,
+                textures: textures.into_values().collect(),
+                sprites: sprites,
+                entities: entities.into_iter().map(|e| e.0).collect(),
+                spawn: Spawn { pos: pos, dir: dir },
+            })
+        }
+    }
+}
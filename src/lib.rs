@@ -0,0 +1,42 @@
+#![allow(clippy::module_inception, clippy::redundant_field_names)]
+
+/* 
+    Raycast engine built from Lode's Computer Graphics Tutorial
+    https://lodev.org/cgtutor/raycasting.html
+
+    World       level map, textures, sprites and entities, loaded from a level file
+    Manifest    names the image files levels use, TextureRegistry holds the loaded wall textures by name and id
+    Atlas       wall and sprite textures packed into one GPU texture for Renderer
+    AssetSource directory or Pack file levels, manifests and images are read from
+    Watcher     polls asset files for changes so they can be hot reloaded
+    Ray         one DDA ray per screen column
+    Camera      position, direction and pitch the world is drawn from
+    Renderer    draws the world from a camera with macroquad
+    SoftwareRenderer draws the same frames into memory without a window
+    Player      input and movement, moves a camera around the world
+    Settings    display and player settings, Screen holds the GPU side
+    Controls    keys bound to each player Action, loaded from a config file
+    Gamepad     the connected gamepad, read through Controls alongside the keys
+    Options     command line options for the demo game, in cli
+*/
+
+pub mod camera;
+pub mod level;
+pub mod map;
+pub mod player;
+pub mod raycast;
+pub mod display;
+pub mod render;
+pub mod input;
+pub mod cli;
+pub mod assets;
+
+pub use level::level::{Level, LevelError, Spawn, SpriteDef, AssetRef};
+pub use assets::assets::{Manifest, TextureRegistry, AssetSource, Pack, Watcher, Atlas};
+pub use map::world::{World, Texture, AssetError, Sprite, Entity, Door, DoorState, PushWall};
+pub use raycast::raycast::Ray;
+pub use player::player::Player;
+pub use display::display::{Settings, Screen};
+pub use render::render::{Renderer, SoftwareRenderer, Workers};
+pub use camera::camera::Camera;
+pub use input::input::{Action, Controls, Gamepad, Stick, deadzone};
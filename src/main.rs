@@ -1,51 +1,228 @@
 use macroquad::prelude::*;
-use map::world::World;
-use player::player::Player;
-use display::display::Settings;
-
-mod map;
-mod player;
-mod raycast;
-mod display;
+use raycast::{World, Player, Settings, Screen, Renderer, SoftwareRenderer, Action, Controls, Gamepad, Camera, AssetError, AssetSource, Watcher};
+use raycast::cli::cli::{Options, USAGE};
 
 /* 
-    Simple raycast graphics built from Lode's Computer Graphics Tutorial
-    https://lodev.org/cgtutor/raycasting.html
+    Demo game using the raycast library
 */
 
-fn conf() -> Conf {
+const CONTROLS: &str = "controls.cfg";
+
+fn conf(options: &Options) -> Conf {
     Conf {
         window_title: String::from("Raycast"),
-        window_width: 640,
-        window_height: 480,
-        fullscreen: false,
+        window_width: options.size.0 as i32,
+        window_height: options.size.1 as i32,
+        fullscreen: options.fullscreen,
         ..Default::default()
     }
 }
 
-#[macroquad::main(conf)]
-async fn main() {
-    let world: World = World::new().await;
-    let display_settings: Settings = Settings::new(world.textures.len());
-    let mut player: Player = Player::new(world, display_settings);
+// Render the current view with the software renderer and save it
+fn screenshot(world: &World, player: &Player, ds: &Settings, path: &str) {
+    let mut renderer: SoftwareRenderer = SoftwareRenderer::new(ds);
+    renderer.render(world, ds, &player.camera);
+    match renderer.save_png(path) {
+        Ok(()) => println!("Saved {}", path),
+        Err(e) => eprintln!("Failed to save {}: {}", path, e),
+    }
+}
+
+// Key bindings from the controls file, the defaults if there isn't one yet
+fn load_controls(path: &str) -> Controls {
+    match Controls::load(path) {
+        Ok((controls, warnings)) => {
+            for w in warnings { eprintln!("{}: {}", path, w); }
+            controls
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Controls::default(),
+        Err(e) => {
+            eprintln!("Failed to read {}: {}, using the default controls", path, e);
+            Controls::default()
+        }
+    }
+}
+
+// Settings from the settings file on top of the defaults, if there is one yet
+fn load_settings(ds: &mut Settings, path: &str) {
+    match ds.load(path) {
+        Ok(warnings) => for w in warnings { eprintln!("{}: {}, using the default", path, w); },
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => (),
+        Err(e) => eprintln!("Failed to read {}: {}, using the default settings", path, e),
+    }
+}
+
+fn save_settings(ds: &Settings, path: &str) {
+    if let Err(e) = ds.save(path) { eprintln!("Failed to save {}: {}", path, e); }
+}
+
+// Settings file first, then anything given on the command line
+fn settings(options: &Options, width: f32, height: f32, num_textures: usize) -> Settings {
+    let mut ds: Settings = Settings::new(width, height, num_textures);
+    load_settings(&mut ds, &options.settings);
+    if let Some((x, y)) = options.resolution {
+        ds.resolution_x = x as f32;
+        ds.resolution_y = y as f32;
+    }
+    ds
+}
+
+fn main() {
+    let options: Options = match Options::parse(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            std::process::exit(2);
+        }
+    };
+    if options.help {
+        println!("{}", USAGE);
+    } else if options.headless {
+        if let Err(e) = headless(&options) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    } else {
+        macroquad::Window::from_config(conf(&options), game(options));
+    }
+}
+
+fn open_assets(options: &Options) -> Result<AssetSource, String> {
+    let assets: AssetSource = AssetSource::open(&options.assets).map_err(|e| format!("Failed to open assets {}: {}", options.assets, e))?;
+    #[cfg(feature = "embedded-assets")]
+    let assets: AssetSource = AssetSource::Layers(vec![assets, AssetSource::Embedded(raycast::assets::assets::EMBEDDED)]);
+    Ok(assets)
+}
+
+// Missing textures show up as a checkerboard so the level can still be played
+fn load_world(assets: &AssetSource, level: &str) -> Result<World, String> {
+    let (world, missing): (World, Vec<AssetError>) = World::load_with_placeholders(assets, level)
+        .map_err(|e| format!("Failed to load level {}: {}", level, e))?;
+    for e in missing { eprintln!("{}, using the placeholder", e); }
+    Ok(world)
+}
+
+/*
+    Reloads changed images in place, a changed level file or manifest reloads the whole world.
+    Returns true if the world was replaced, its files need watching again then
+*/
+fn hot_reload(changed: Vec<String>, assets: &AssetSource, options: &Options, world: &mut World, ds: &mut Settings) -> bool {
+    if changed.iter().any(|f| world.files[..2].contains(f)) {
+        match load_world(assets, &options.level) {
+            Ok(mut new) => {
+                if let Err(e) = new.upload() {
+                    eprintln!("Failed to load level {}: {}, keeping the old level", options.level, e);
+                    return false;
+                }
+                world.unload();
+                *world = new;
+                ds.num_textures = world.textures.len();
+                ds.floor_texture = ds.floor_texture.min(ds.num_textures.saturating_sub(1));
+                ds.ceil_texture = ds.ceil_texture.min(ds.num_textures.saturating_sub(1));
+                println!("Reloaded {}", options.level);
+                return true;
+            }
+            Err(e) => eprintln!("{}, keeping the old level", e),
+        }
+        return false;
+    }
+    for file in changed {
+        match world.reload_texture(assets, &file) {
+            Ok(_) => println!("Reloaded {}", file),
+            Err(e) => eprintln!("{}, keeping the old texture", e),
+        }
+    }
+    false
+}
+
+/*
+    Renders frames without a window, turning a full circle from the spawn point while doors and
+    push walls carry on moving, and writes them to <out>/frame_0000.png ...
+*/
+fn headless(options: &Options) -> Result<(), String> {
+    let mut world: World = load_world(&open_assets(options)?, &options.level)?;
+    let mut ds: Settings = settings(options, options.size.0 as f32, options.size.1 as f32, world.textures.len());
+    // Drawn at the render resolution then scaled up to the window size without smoothing, like the game window
+    ds.resize(ds.resolution_x.floor(), ds.resolution_y.floor());
+    std::fs::create_dir_all(&options.out).map_err(|e| format!("Failed to create {}: {}", options.out, e))?;
+
+    let mut renderer: SoftwareRenderer = SoftwareRenderer::new(&ds);
+    let mut camera: Camera = Camera::new(world.spawn.pos, world.spawn.dir);
+    let turn: f32 = std::f32::consts::TAU / options.frames as f32;
+    for frame in 0..options.frames {
+        renderer.render(&world, &ds, &camera);
+        let path: String = format!("{}/frame_{:04}.png", options.out, frame);
+        let image: image::RgbaImage = image::RgbaImage::from_raw(renderer.width as u32, renderer.height as u32, renderer.frame.clone()).unwrap();
+        image::imageops::resize(&image, options.size.0, options.size.1, image::imageops::FilterType::Nearest)
+            .save(&path).map_err(|e| format!("Failed to save {}: {}", path, e))?;
+
+        camera.rotate(turn);
+        world.update(1.0 / 60.0, camera.pos, ds.player_radius);
+    }
+    println!("Saved {} frames to {}", options.frames, options.out);
+    Ok(())
+}
+
+async fn game(options: Options) {
+    let assets: AssetSource = match open_assets(&options) {
+        Ok(assets) => assets,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
+    let mut world: World = match load_world(&assets, &options.level) {
+        Ok(world) => world,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
+    if let Err(e) = world.upload() {
+        eprintln!("Failed to load level {}: {}", options.level, e);
+        return;
+    }
+    // Only files in a directory can change while the game runs
+    let mut watcher: Option<Watcher> = assets.dir().map(|dir| Watcher::new(dir, &world.files));
+    let mut ds: Settings = settings(&options, screen_width(), screen_height(), world.textures.len());
+    let mut controls: Controls = load_controls(CONTROLS);
+    match Gamepad::new() {
+        Ok(gamepad) => controls.gamepad = gamepad,
+        Err(e) => eprintln!("No gamepad support: {}", e),
+    }
+    let mut screen: Screen = match Screen::new(&ds) {
+        Ok(screen) => screen,
+        Err(e) => {
+            eprintln!("Failed to compile the CRT shader: {}, shaders are off", e);
+            Screen::without_shaders(&ds)
+        }
+    };
+    let mut renderer: Renderer = Renderer::new(&ds);
+    let mut player: Player = Player::new(&world);
     
     loop {
-        if is_key_pressed(KeyCode::Escape) {
+        controls.update(); // Keys and gamepad buttons pressed since last frame
+        if controls.pressed(Action::Quit) {
+            save_settings(&ds, &options.settings);
             break;
         }
-        set_camera(&player.ds.camera);
+        if controls.pressed(Action::Screenshot) { screenshot(&world, &player, &ds, "screenshot.png"); }
 
-        /* 
-            Drawing textured floors is super slow.
-            I did have a super cool fast method using from_rgba8, but as the drop implementation for the texture
-            doesn't deallocate memory, it crashes. Will fix later, using slow method for now.
-        */
-        if player.ds.draw_floors { player.draw_floor(); }
-        if player.ds.draw_walls { player.raycast(); } // Walls are drawn here
-        if player.ds.draw_sprites { player.draw_sprites(); }
+        set_camera(&screen.camera);
+        renderer.draw(&world, &player.camera, &ds);
         
-        player.movement();  // Get player input
-        player.ds.draw_ui();
+        let open: bool = ds.settings;
+        player.movement(&mut world, &mut ds, &controls);  // Get player input
+        if open && !ds.settings { save_settings(&ds, &options.settings); } // Settings window closed
+        world.update(get_frame_time(), player.camera.pos, ds.player_radius);
+        if let Some(w) = &mut watcher {
+            let changed: Vec<String> = w.poll(get_frame_time());
+            if hot_reload(changed, &assets, &options, &mut world, &mut ds) { watcher = assets.dir().map(|dir| Watcher::new(dir, &world.files)); }
+        }
+        ds.draw_ui(&mut screen, &mut controls);
+        if controls.take_changed() {
+            if let Err(e) = controls.save(CONTROLS) { eprintln!("Failed to save {}: {}", CONTROLS, e); }
+        }
         next_frame().await
     }
 }
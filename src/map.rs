@@ -1,153 +1,503 @@
 pub mod world {
+    use std::collections::HashMap;
+    use std::fmt;
     use macroquad::prelude::*;
+    use crate::level::level::{Level, LevelError, Spawn, AssetRef};
+    use crate::assets::assets::{Manifest, TextureRegistry, AssetSource, Atlas};
 
     pub struct Sprite {
         pub x: f32,
         pub y: f32,
-        pub dist: f32,
-        pub texture: Texture2D,
+        pub texture: usize, // Index into World::sprite_textures
     }
+    #[derive(Clone)]
     pub struct Texture {
-        pub texture: Texture2D,
         pub texture_data: Vec<Color>,
+        pub width: u32,
+        pub height: u32,
+        pub average: Color, // Used for the tops of walls shorter than the camera
+        pub path: String, // Where it was loaded from, kept for placeholders so they reload once the file is fixed
     }
     pub enum Entity { 
-        Door((u32, u32), (i32, i32)), 
-        Power((u32, u32), (i32, i32))
+        Door((i32, i32)), 
+        Power((u32, u32), (i32, i32)),
+        PushWall((i32, i32), u32), // Cell and how many cells it slides
      }
 
-    async fn load_textures(texture_strings: Vec<&str>) -> Vec<Texture> {
-        let mut textures: Vec<Texture> = Vec::new();
-        for n in texture_strings {
-            let texture: Texture2D = load_texture(n).await.unwrap();
+    const DOOR_TIME: f32 = 1.0; // Seconds to slide open or shut
+    const DOOR_WAIT: f32 = 3.0; // Seconds a door stays open before closing
+
+    #[derive(Clone, Copy, PartialEq, Debug)]
+    pub enum DoorState { Closed, Opening, Open, Closing }
+
+    /*
+        Thin door drawn across the middle of its cell, slides sideways to open
+    */
+    pub struct Door {
+        pub cell: (i32, i32),
+        pub side: bool, // false spans the cell at x = row + 0.5, true at y = col + 0.5
+        pub open: f32, // 0 closed .. 1 fully open
+        pub state: DoorState,
+        timer: f32, // Time spent open
+    }
+
+    const PUSH_SPEED: f32 = 1.0; // Cells per second
+
+    /*
+        Secret wall that slides away from the player until it hits something or runs out of cells
+    */
+    pub struct PushWall {
+        pub cell: (i32, i32), // Cell it last sat in
+        pub texture: u32,
+        pub height: f32, // Wall height it carries along
+        pub cells: u32, // Cells left to move
+        pub dir: (i32, i32),
+        pub moved: f32, // 0 .. 1 of the way to the next cell
+        pub moving: bool,
+    }
+
+    impl PushWall {
+        // Top left corner of the block in map coordinates
+        pub fn corner(&self) -> Vec2 {
+            vec2(self.cell.0 as f32 + self.dir.0 as f32 * self.moved, self.cell.1 as f32 + self.dir.1 as f32 * self.moved)
+        }
+
+        // Whether a moving block covers part of a cell
+        pub fn covers(&self, r: i32, c: i32) -> bool {
+            self.moving && ((r, c) == self.cell || (r, c) == (self.cell.0 + self.dir.0, self.cell.1 + self.dir.1))
+        }
+    }
+
+    /*
+        Why an image file couldn't be loaded
+    */
+    #[derive(Debug)]
+    pub enum AssetError {
+        Read(String, std::io::Error),
+        Decode(String, image::ImageError),
+        AtlasSize((u32, u32), u32), // Size the textures packed to, and the largest the GPU takes
+    }
+
+    impl fmt::Display for AssetError {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            match self {
+                AssetError::Read(path, e) => write!(f, "couldn't read {}: {}", path, e),
+                AssetError::Decode(path, e) => write!(f, "couldn't decode {}: {}", path, e),
+                AssetError::AtlasSize((w, h), max) => write!(f, "textures need a {}x{} atlas, the largest allowed is {}x{}", w, h, max, max),
+            }
+        }
+    }
+
+    impl std::error::Error for AssetError {}
+
+    const PLACEHOLDER_SIZE: u32 = 64;
+    const PLACEHOLDER_SQUARE: u32 = 8; // Pixels per checker
+
+    impl Texture {
+        pub fn load(assets: &AssetSource, path: &str) -> Result<Texture, AssetError> {
+            let bytes: Vec<u8> = assets.read(path).map_err(|e| AssetError::Read(path.to_string(), e))?;
+            let img: image::RgbaImage = image::load_from_memory(&bytes).map_err(|e| AssetError::Decode(path.to_string(), e))?.to_rgba8();
+            let texture_data: Vec<Color> = img.pixels().map(|p| Color::from_rgba(p[0], p[1], p[2], p[3])).collect();
+            let mut texture: Texture = Texture::new(texture_data, img.width(), img.height());
+            texture.path = path.to_string();
+            Ok(texture)
+        }
+
+        // Magenta and black checkerboard drawn in place of textures that failed to load
+        pub fn placeholder() -> Texture {
             let mut texture_data: Vec<Color> = Vec::new();
-            for p in load_image(n).await.unwrap().get_image_data().to_vec() {
-                let c: Color = p.into();
-                texture_data.push(c);
+            for y in 0..PLACEHOLDER_SIZE {
+                for x in 0..PLACEHOLDER_SIZE {
+                    let odd: bool = (x / PLACEHOLDER_SQUARE + y / PLACEHOLDER_SQUARE) % 2 == 1;
+                    texture_data.push(if odd { BLACK } else { MAGENTA });
+                }
+            }
+            Texture::new(texture_data, PLACEHOLDER_SIZE, PLACEHOLDER_SIZE)
+        }
+
+        fn new(texture_data: Vec<Color>, width: u32, height: u32) -> Texture {
+            let mut sum: Vec4 = Vec4::ZERO;
+            for c in &texture_data { sum += c.to_vec(); }
+            Texture { 
+                average: Color::from_vec(sum / texture_data.len().max(1) as f32),
+                texture_data: texture_data, 
+                width: width, 
+                height: height,
+                path: String::new(),
+            }
+        }
+
+        // texture_data as RGBA bytes
+        pub fn image(&self) -> Image {
+            Image {
+                bytes: self.texture_data.iter()
+                    .flat_map(|c| [c.r, c.g, c.b, c.a])
+                    .map(|v| (v * 255.0).round() as u8)
+                    .collect(),
+                width: self.width as u16,
+                height: self.height as u16,
             }
-            textures.push(Texture { texture: texture, texture_data: texture_data });
         }
-        textures
     }
+
     pub struct World {
         pub world_map: Vec<u32>,
-        pub textures: Vec<Texture>,
+        pub floor_map: Option<Vec<u32>>, // Per cell floor texture ids, None uses Settings::floor_texture
+        pub ceil_map: Option<Vec<u32>>, // Per cell ceiling texture ids, 0 is open sky
+        pub height_map: Option<Vec<f32>>, // Per cell wall heights, None is every wall 1.0 high
+        pub max_height: f32, // Tallest wall, rays stop at walls this tall
+        pub textures: TextureRegistry, // Wall textures by the ids in the map
+        pub sprite_textures: Vec<Texture>,
+        pub atlas: Atlas, // Wall and sprite textures on the GPU, empty until uploaded
         pub sprite_map: Vec<Sprite>,
         pub entities: Vec<Entity>,
-        pub columns: usize,
+        pub doors: Vec<Door>,
+        pub push_walls: Vec<PushWall>,
+        pub width: usize, // Columns
+        pub height: usize, // Rows
+        pub outside: u32, // Returned by get for cells off the map
+        pub sky: Option<Texture>, // Panorama drawn where there is no ceiling
+        pub spawn: Spawn,
+        pub files: Vec<String>, // Level file, manifest, then the images it was loaded from, for hot reloading
     }
 
     impl World {
-        pub async fn new() -> World {
-
-            let world_map: Vec<u32> = vec![
-                1,1,1,1,1,1,1,1,1,1,1,3,2,3,2,3,2,3,2,3,2,3,2,3,
-                1,0,0,0,0,0,0,0,0,0,1,2,0,0,0,0,0,0,0,0,0,0,0,2,
-                1,0,5,5,0,5,5,5,0,1,1,3,0,0,0,0,0,0,0,0,0,0,0,3,
-                1,0,0,0,0,0,0,0,0,0,12,0,0,0,0,0,0,0,0,0,0,0,0,2,
-                1,0,5,5,0,5,5,5,0,1,1,2,0,0,0,0,0,0,0,0,0,0,0,3,
-                1,0,0,0,0,0,0,0,0,0,1,3,0,0,0,0,0,6,6,6,0,6,6,6,
-                1,1,1,1,0,1,1,1,1,1,1,2,3,2,3,2,3,6,0,6,0,6,0,6,
-                5,5,5,5,0,5,5,5,5,0,8,0,8,0,8,0,7,6,0,0,0,0,0,6,
-                5,5,0,0,0,0,0,0,5,7,0,7,0,7,0,7,7,6,0,0,0,0,0,6,
-                5,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,7,6,0,0,0,0,0,6,
-                5,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,7,6,0,6,0,6,0,6,
-                5,5,0,0,0,0,0,0,5,7,0,7,0,7,0,7,7,6,6,8,0,8,6,6,
-                5,5,5,5,0,5,5,5,5,7,8,7,8,7,8,7,7,8,8,0,0,0,8,8,
-                2,2,2,2,0,2,2,2,2,7,7,7,0,7,7,0,7,8,0,0,0,0,0,8,
-                2,2,0,0,0,0,0,2,2,7,0,0,0,0,0,0,7,8,0,0,0,0,0,8,
-                2,0,0,0,0,0,0,0,2,7,0,0,0,0,0,0,7,8,0,0,0,0,0,8,
-                2,0,0,0,0,0,0,0,2,7,7,7,7,7,7,0,7,8,8,0,0,0,8,8,
-                2,0,0,0,0,0,0,0,2,1,1,1,1,1,2,13,7,0,0,8,0,8,0,4,
-                9,9,9,9,11,9,9,9,9,1,0,0,0,1,1,0,4,0,4,0,0,0,4,4,
-                9,0,0,0,0,0,0,0,1,0,0,0,0,0,1,4,0,4,0,4,0,4,0,4,
-                9,0,0,0,0,0,0,0,12,0,0,0,0,0,0,0,0,0,0,0,0,0,0,4,
-                9,0,0,0,0,0,0,0,1,0,0,0,0,0,1,4,0,4,0,4,0,4,0,4,
-                9,9,0,0,0,0,0,9,1,1,0,0,0,1,1,0,4,0,4,0,0,0,4,4,
-                9,9,9,9,9,9,9,9,2,1,1,1,1,1,1,4,4,4,4,4,4,4,4,4
-            ];
-
-            let entities = vec![
-                Entity::Door((0, 12), (20, 8)),
-                Entity::Door((0, 11), (18, 4)),
-                Entity::Door((0, 8), (12, 12)),
-                Entity::Power((14, 13), (17, 15)),
-                Entity::Door((0, 12), (3, 10)),
-            ];
-
-            let sprites: Vec<Texture2D> = vec![
-                load_texture("src/assets/barrel.png").await.unwrap(), 
-                load_texture("src/assets/pillar.png").await.unwrap(), 
-                load_texture("src/assets/greenlight.png").await.unwrap(), 
-                load_texture("src/assets/jerma.png").await.unwrap(), 
-            ];
-
-            let sprite_map: Vec<Sprite> = vec![
-                Sprite {x: 13.5,    y: 15.5,    texture: sprites[3],     dist: 0.0},
-                Sprite {x: 20.5,    y: 11.5,    texture: sprites[2],     dist: 0.0},
-                Sprite {x: 18.5,    y: 4.5,     texture: sprites[2],     dist: 0.0},
-                Sprite {x: 10.0,    y: 4.5,     texture: sprites[2],     dist: 0.0},
-                Sprite {x: 10.0,    y: 12.5,    texture: sprites[2],     dist: 0.0},
-                Sprite {x: 3.5,     y: 6.5,     texture: sprites[2],     dist: 0.0},
-                Sprite {x: 3.5,     y: 20.5,    texture: sprites[2],     dist: 0.0},
-                Sprite {x: 3.5,     y: 14.5,    texture: sprites[2],     dist: 0.0},
-                Sprite {x: 14.5,    y: 20.5,    texture: sprites[2],     dist: 0.0},
-                Sprite {x: 18.5,    y: 10.5,    texture: sprites[1],     dist: 0.0},
-                Sprite {x: 18.5,    y: 11.5,    texture: sprites[1],     dist: 0.0},
-                Sprite {x: 18.5,    y: 12.5,    texture: sprites[1],     dist: 0.0},
-                Sprite {x: 21.5,    y: 1.5,     texture: sprites[0],     dist: 0.0},
-                Sprite {x: 15.5,    y: 1.5,     texture: sprites[0],     dist: 0.0},
-                Sprite {x: 16.0,    y: 1.8,     texture: sprites[0],     dist: 0.0},
-                Sprite {x: 16.2,    y: 1.2,     texture: sprites[0],     dist: 0.0},
-                Sprite {x: 3.5,     y: 2.5,     texture: sprites[0],     dist: 0.0},
-                Sprite {x: 9.5,     y: 15.5,    texture: sprites[0],     dist: 0.0},
-                Sprite {x: 10.0,    y: 15.1,    texture: sprites[0],     dist: 0.0},
-                Sprite {x: 10.5,    y: 15.8,    texture: sprites[0],     dist: 0.0},
-            ];
-
-            let textures_names: Vec<&str> = vec![
-                "src/assets/red_brick.png",         // 1
-                "src/assets/concrete_pattern.png",  // 2
-                "src/assets/smooth_concrete.png",   // 3
-                "src/assets/mossy_cobble.png",      // 4
-                "src/assets/metal_floor.png",       // 5
-                "src/assets/rose_dark.png",         // 6
-                "src/assets/rose_pattern.png",      // 7
-                "src/assets/floral_pattern.png",    // 8
-                "src/assets/wood_plank.png",        // 9
-                "src/assets/log_plank.png",         // 10
-                "src/assets/wooden_double_door.png",// 11
-                "src/assets/metal_double_door.png", // 12
-                "src/assets/button_off.png",        // 13
-                "src/assets/button_on.png",         // 14
-            ];
-
-            let textures: Vec<Texture> = load_textures(textures_names).await;
-
-            World { 
-                world_map: world_map, 
-                columns: 24,
+        // Loads a level and its textures onto the GPU, needs a window
+        pub fn load(path: &str) -> Result<World, LevelError> {
+            let mut world: World = World::load_headless(path)?;
+            world.upload().map_err(LevelError::Asset)?;
+            Ok(world)
+        }
+
+        // Loads a level and keeps textures in memory only, for the software renderer
+        pub fn load_headless(path: &str) -> Result<World, LevelError> {
+            Ok(World::build(&AssetSource::default(), path, false)?.0)
+        }
+
+        /*
+            Loads a level in memory like load_headless, but textures that fail to load are swapped for the
+            checkerboard placeholder and returned alongside the world instead of failing the whole level.
+            The level, its manifest and images are all read from assets
+        */
+        pub fn load_with_placeholders(assets: &AssetSource, path: &str) -> Result<(World, Vec<AssetError>), LevelError> {
+            World::build(assets, path, true)
+        }
+
+        /*
+            Needs a window, packs the wall and sprite textures into the atlas Renderer draws from.
+            The sky and floors are drawn on the CPU so they stay in memory only
+        */
+        pub fn upload(&mut self) -> Result<(), AssetError> {
+            let atlas: Atlas = Atlas::new(&self.textures, &self.sprite_textures, Atlas::max_size())?;
+            self.atlas.unload();
+            self.atlas = atlas;
+            self.atlas.upload();
+            Ok(())
+        }
+
+        // Frees the atlas
+        pub fn unload(&mut self) {
+            self.atlas.unload();
+        }
+
+        /*
+            Reloads every texture loaded from path, walls, sprites and the sky can share a file.
+            The atlas is packed again if it was on the GPU. Returns how many were reloaded,
+            if the file can't be loaded the old textures are kept, if the atlas can't be packed the old one is
+        */
+        pub fn reload_texture(&mut self, assets: &AssetSource, path: &str) -> Result<usize, AssetError> {
+            let mut reloaded: usize = 0;
+            let mut loaded: Option<Texture> = None;
+            for t in self.textures.iter_mut().chain(self.sprite_textures.iter_mut()).chain(self.sky.iter_mut()) {
+                if t.path != path { continue; }
+                let new: &Texture = match &loaded {
+                    Some(new) => new,
+                    None => loaded.insert(Texture::load(assets, path)?),
+                };
+                *t = new.clone();
+                reloaded += 1;
+            }
+            if reloaded > 0 && self.atlas.uploaded() { self.upload()?; }
+            Ok(reloaded)
+        }
+
+        fn build(assets: &AssetSource, path: &str, placeholders: bool) -> Result<(World, Vec<AssetError>), LevelError> {
+            let source: String = assets.read_to_string(path).map_err(|e| LevelError::File(path.to_string(), e))?;
+            let level: Level = Level::parse(&source)?;
+
+            let manifest: Manifest = Manifest::load(assets, &level.manifest)?;
+
+            // Every name has to be in the manifest, only the image files are allowed to be missing
+            let mut files: Vec<String> = vec![path.to_string(), level.manifest.clone()];
+            let mut missing: Vec<AssetError> = Vec::new();
+            let mut load = |asset: &AssetRef| -> Result<Texture, LevelError> {
+                let path: &str = manifest.path(&asset.name)
+                    .ok_or_else(|| asset.error(format!("'{}' is not in the manifest {}", asset.name, level.manifest)))?;
+                if !files.iter().any(|f| f == path) { files.push(path.to_string()); }
+                match Texture::load(assets, path) {
+                    Ok(t) => Ok(t),
+                    Err(e) if placeholders => { 
+                        missing.push(e); 
+                        let mut t: Texture = Texture::placeholder();
+                        t.path = path.to_string();
+                        Ok(t)
+                    }
+                    Err(e) => Err(LevelError::Asset(e)),
+                }
+            };
+
+            // Sprites share textures, only load each file once
+            let mut sprite_ids: HashMap<String, usize> = HashMap::new();
+            let mut sprite_textures: Vec<Texture> = Vec::new();
+            let mut sprite_map: Vec<Sprite> = Vec::new();
+            for s in &level.sprites {
+                let texture: usize = match sprite_ids.get(&s.name.name) {
+                    Some(id) => *id,
+                    None => {
+                        sprite_textures.push(load(&s.name)?);
+                        sprite_ids.insert(s.name.name.clone(), sprite_textures.len() - 1);
+                        sprite_textures.len() - 1
+                    }
+                };
+                sprite_map.push(Sprite { x: s.x, y: s.y, texture: texture });
+            }
+
+            let mut textures: TextureRegistry = TextureRegistry::default();
+            for t in &level.textures { textures.add(&t.name, load(t)?); }
+            let sky: Option<Texture> = level.sky.as_ref().map(&mut load).transpose()?;
+
+            // Doors face along the corridor they close off, between the walls either side of them
+            let wall = |r: i32, c: i32| -> bool {
+                r >= 0 && c >= 0 && (r as usize) < level.height && (c as usize) < level.width
+                && level.world_map[level.width * r as usize + c as usize] > 0
+            };
+            let mut doors: Vec<Door> = Vec::new();
+            let mut push_walls: Vec<PushWall> = Vec::new();
+            for entity in &level.entities {
+                match *entity {
+                    Entity::Door(cell) => doors.push(Door {
+                        cell: cell,
+                        side: !(wall(cell.0, cell.1 - 1) && wall(cell.0, cell.1 + 1)),
+                        open: 0.0,
+                        state: DoorState::Closed,
+                        timer: 0.0,
+                    }),
+                    Entity::PushWall(cell, cells) => push_walls.push(PushWall {
+                        cell: cell,
+                        texture: level.world_map[level.width * cell.0 as usize + cell.1 as usize],
+                        height: 1.0,
+                        cells: cells,
+                        dir: (0, 0),
+                        moved: 0.0,
+                        moving: false,
+                    }),
+                    Entity::Power(..) => (),
+                }
+            }
+
+            let world: World = World { 
+                files: files,
+                world_map: level.world_map, 
+                floor_map: level.floor_map,
+                ceil_map: level.ceil_map,
+                max_height: level.height_map.as_ref().map_or(1.0, |h| h.iter().fold(1.0, |a: f32, b| a.max(*b))),
+                height_map: level.height_map,
+                width: level.width,
+                height: level.height,
+                outside: level.outside,
+                sky: sky,
                 textures: textures,
-                entities: entities,
+                sprite_textures: sprite_textures,
+                atlas: Atlas::default(),
+                entities: level.entities,
+                doors: doors,
+                push_walls: push_walls,
                 sprite_map: sprite_map, 
-            }
+                spawn: level.spawn,
+            };
+            Ok((world, missing))
         }
         
+        pub fn contains(&self, r: i32, c: i32) -> bool {
+            r >= 0 && c >= 0 && (r as usize) < self.height && (c as usize) < self.width
+        }
+
         pub fn get(&self, r: i32, c: i32) -> u32 {
-            self.world_map[self.columns * r as usize + c as usize]
+            if !self.contains(r, c) { return self.outside; }
+            self.world_map[self.width * r as usize + c as usize]
+        }
+
+        pub fn door(&self, r: i32, c: i32) -> Option<&Door> {
+            self.doors.iter().find(|d| d.cell == (r, c))
+        }
+
+        // Whether a cell blocks movement, doors only stop blocking once fully open
+        pub fn solid(&self, r: i32, c: i32) -> bool {
+            if self.push_walls.iter().any(|p| p.covers(r, c)) { return true; }
+            match self.door(r, c) {
+                Some(door) => door.state != DoorState::Open,
+                None => self.get(r, c) > 0,
+            }
+        }
+
+        // Cells covered by a player of the given radius at pos, as (first row, last row, first col, last col)
+        fn footprint(pos: Vec2, radius: f32) -> (i32, i32, i32, i32) {
+            ((pos.x - radius).floor() as i32, (pos.x + radius).floor() as i32, (pos.y - radius).floor() as i32, (pos.y + radius).floor() as i32)
+        }
+
+        // Whether a player of the given radius at pos overlaps a cell
+        pub fn touches(&self, cell: (i32, i32), pos: Vec2, radius: f32) -> bool {
+            let (r0, r1, c0, c1): (i32, i32, i32, i32) = World::footprint(pos, radius);
+            (r0..=r1).contains(&cell.0) && (c0..=c1).contains(&cell.1)
+        }
+
+        // Area of a cell covered by a player of the given radius at pos
+        fn overlap(cell: (i32, i32), pos: Vec2, radius: f32) -> f32 {
+            let along = |p: f32, c: i32| ((p + radius).min(c as f32 + 1.0) - (p - radius).max(c as f32)).max(0.0);
+            along(pos.x, cell.0) * along(pos.y, cell.1)
+        }
+
+        /*
+            Whether a player of the given radius moving from one position to another would run into
+            a solid cell. Moves out of a wall it already overlaps are allowed so it can't get stuck
+        */
+        pub fn blocked(&self, from: Vec2, to: Vec2, radius: f32) -> bool {
+            let (r0, r1, c0, c1): (i32, i32, i32, i32) = World::footprint(to, radius);
+            for r in r0..=r1 {
+                for c in c0..=c1 {
+                    if self.solid(r, c) && World::overlap((r, c), to, radius) > World::overlap((r, c), from, radius) { return true; }
+                }
+            }
+            false
+        }
+
+        // Open a shut door or shut an open one, doors won't close on the player
+        pub fn use_door(&mut self, cell: (i32, i32), pos: Vec2, radius: f32) {
+            let inside: bool = self.touches(cell, pos, radius);
+            if let Some(door) = self.doors.iter_mut().find(|d| d.cell == cell) {
+                door.state = match door.state {
+                    DoorState::Closed | DoorState::Closing => DoorState::Opening,
+                    DoorState::Open | DoorState::Opening if !inside => DoorState::Closing,
+                    state => state,
+                };
+            }
+        }
+
+        /*
+            Start a push wall sliding along dir. Does nothing while it's moving, once it has slid all its cells,
+            or if the next cell is blocked. A wall stopped early can be pushed again for the cells it has left
+        */
+        pub fn push(&mut self, cell: (i32, i32), dir: (i32, i32)) {
+            let i: usize = match self.push_walls.iter().position(|p| p.cell == cell && !p.moving && p.cells > 0) {
+                Some(i) => i,
+                None => return,
+            };
+            if self.get(cell.0, cell.1) == 0 || !self.free(cell.0 + dir.0, cell.1 + dir.1) { return; }
+
+            self.world_map[self.width * cell.0 as usize + cell.1 as usize] = 0;
+            let height: f32 = self.wall_height(cell.0, cell.1);
+            let wall: &mut PushWall = &mut self.push_walls[i];
+            wall.height = height;
+            wall.dir = dir;
+            wall.moving = true;
+        }
+
+        // Whether a push wall can slide into a cell, doors block it even when open
+        fn free(&self, r: i32, c: i32) -> bool {
+            self.contains(r, c) && !self.solid(r, c) && self.door(r, c).is_none()
+        }
+
+        // Move doors and push walls along, pos and radius are the player's
+        pub fn update(&mut self, dt: f32, pos: Vec2, radius: f32) {
+            for i in 0..self.push_walls.len() {
+                if !self.push_walls[i].moving { continue; }
+                self.push_walls[i].moved += dt * PUSH_SPEED;
+                while self.push_walls[i].moving && self.push_walls[i].moved >= 1.0 {
+                    let wall: &mut PushWall = &mut self.push_walls[i];
+                    wall.cell = (wall.cell.0 + wall.dir.0, wall.cell.1 + wall.dir.1);
+                    wall.moved -= 1.0;
+                    wall.cells -= 1;
+                    wall.moving = false; // So it doesn't block itself below
+                    let (cell, next, texture, height, cells): ((i32, i32), (i32, i32), u32, f32, u32) = 
+                        (wall.cell, (wall.cell.0 + wall.dir.0, wall.cell.1 + wall.dir.1), wall.texture, wall.height, wall.cells);
+
+                    // Keep going while there are cells left and the next one is free
+                    if cells > 0 && self.free(next.0, next.1) && !self.touches(next, pos, radius) {
+                        self.push_walls[i].moving = true;
+                    } else { // Stopped, the block is part of the map again
+                        self.push_walls[i].moved = 0.0;
+                        let index: usize = self.width * cell.0 as usize + cell.1 as usize;
+                        self.world_map[index] = texture;
+                        if let Some(h) = &mut self.height_map { h[index] = height; }
+                    }
+                }
+            }
+
+            let footprint: (i32, i32, i32, i32) = World::footprint(pos, radius);
+            for door in &mut self.doors {
+                let inside: bool = (footprint.0..=footprint.1).contains(&door.cell.0) && (footprint.2..=footprint.3).contains(&door.cell.1);
+                match door.state {
+                    DoorState::Opening => {
+                        door.open += dt / DOOR_TIME;
+                        if door.open >= 1.0 { door.open = 1.0; door.state = DoorState::Open; door.timer = 0.0; }
+                    }
+                    DoorState::Open => {
+                        door.timer += dt;
+                        if door.timer >= DOOR_WAIT && !inside { door.state = DoorState::Closing; }
+                    }
+                    DoorState::Closing if inside => door.state = DoorState::Opening,
+                    DoorState::Closing => {
+                        door.open -= dt / DOOR_TIME;
+                        if door.open <= 0.0 { door.open = 0.0; door.state = DoorState::Closed; }
+                    }
+                    DoorState::Closed => (),
+                }
+            }
         }
 
-        pub fn change(&mut self, pos: Vec3,  texture: (u32, u32), coords: (i32, i32)) { // Change texture in map
-            if self.get(coords.0, coords.1) != texture.0 { 
-                self.world_map[self.columns * coords.0 as usize + coords.1 as usize] = texture.0;
+        // Height of the wall in a cell, 1.0 is as tall as a cell is wide
+        pub fn wall_height(&self, r: i32, c: i32) -> f32 {
+            match &self.height_map {
+                Some(h) if self.contains(r, c) => h[self.width * r as usize + c as usize],
+                _ => 1.0,
+            }
+        }
+
+        // Index into textures of a floor or ceiling layer at a cell, None where there is no surface or no texture to draw it with
+        fn surface(&self, layer: &Option<Vec<u32>>, r: i32, c: i32, default: usize) -> Option<usize> {
+            match layer {
+                None if default >= self.textures.len() => None,
+                None => Some(default),
+                Some(_) if !self.contains(r, c) => None,
+                Some(l) => match l[self.width * r as usize + c as usize] {
+                    0 => None,
+                    id => Some(id as usize - 1),
+                }
+            }
+        }
+
+        pub fn floor_texture(&self, r: i32, c: i32, default: usize) -> Option<usize> {
+            self.surface(&self.floor_map, r, c, default)
+        }
+
+        pub fn ceil_texture(&self, r: i32, c: i32, default: usize) -> Option<usize> {
+            self.surface(&self.ceil_map, r, c, default)
+        }
+
+        pub fn change(&mut self, pos: Vec2,  texture: (u32, u32), coords: (i32, i32)) { // Change texture in map
+            if !self.contains(coords.0, coords.1) { return; }
+            let i: usize = self.width * coords.0 as usize + coords.1 as usize;
+
+            if self.world_map[i] != texture.0 { 
+                self.world_map[i] = texture.0;
             }
             else if coords != (pos.x as i32, pos.y as i32) { 
-                self.world_map[self.columns * coords.0 as usize + coords.1 as usize] = texture.1;
+                self.world_map[i] = texture.1;
             }
         }
 
-        pub fn texture(&self, map: (i32, i32)) -> Texture2D {
-            self.textures[(self.get(map.0, map.1) - 1) as usize].texture
+        pub fn texture(&self, id: u32) -> &Texture {
+            self.textures.get(id)
         }
 
         // Maybe combine these shading functions ?
@@ -170,8 +520,8 @@ pub mod world {
                 color.b /= dist * shading_multiplier;
             }
             
-            if shadows { 
-                if side { color.r /= 1.5; color.g /= 1.5; color.b /= 1.5; } 
+            if shadows && side { 
+                color.r /= 1.5; color.g /= 1.5; color.b /= 1.5;
             }
 
             color
@@ -1,40 +1,47 @@
 pub mod raycast {
     use macroquad::prelude::*;
-    use crate::player::player::Player;
+    use crate::camera::camera::Camera;
+    use crate::map::world::{World, Door, PushWall};
 
-    /* 
-        Raycast stuff 
+    /*
+        Raycast stuff
     */
 
+    #[derive(Clone, Copy)]
     pub struct Ray {
-        pub camera: f32,  
-        pub ray_dir: Vec2,  
-        pub map: (i32, i32),  
-        pub side_dist: Vec2,  
-        pub delta_dist: Vec2,  
-        pub perp_wall_dist: f32,  
+        pub pos: Vec2,
+        pub ray_dir: Vec2,
+        pub map: (i32, i32),
+        pub side_dist: Vec2,
+        pub delta_dist: Vec2,
+        pub perp_wall_dist: f32,
         pub step: (i32, i32),
-        pub hit: bool, 
+        pub hit: bool,
         pub side: bool,
+        pub offset: f32, // How far the texture of a sliding door or push wall has moved
+        pub texture: u32, // Texture id of what was hit
+        pub height: f32, // Height of the wall that was hit
+        pub far_dist: f32, // Where the ray comes out of the wall, for drawing its top
     }
 
     impl Ray {
-        pub fn new(x: f32, player: &mut Player) -> Ray {     
-            let camera: f32 = 2.0 * x / player.ds.width - 1.0; 
-            let ray_dir: Vec2 = vec2(player.dir.x + player.plane.x * camera, player.dir.y + player.plane.y * camera);
-            let map: (i32, i32) = (player.pos.x as i32, player.pos.y as i32); 
+        pub fn new(x: f32, width: f32, cam: &Camera) -> Ray {
+            let (pos, dir, plane): (Vec2, Vec2, Vec2) = (cam.pos, cam.dir, cam.plane);
+            let camera: f32 = 2.0 * x / width - 1.0;
+            let ray_dir: Vec2 = vec2(dir.x + plane.x * camera, dir.y + plane.y * camera);
+            let map: (i32, i32) = (pos.x as i32, pos.y as i32);
             let mut side_dist: Vec2 = vec2(0.0, 0.0);
-            let delta_dist: Vec2 = vec2((1.0 / ray_dir.x).abs(), (1.0 / ray_dir.y).abs());  
-            let perp_wall_dist: f32 = 0.0; 
-            let mut step: (i32, i32) = (0, 0); 
+            let delta_dist: Vec2 = vec2((1.0 / ray_dir.x).abs(), (1.0 / ray_dir.y).abs());
+            let perp_wall_dist: f32 = 0.0;
+            let mut step: (i32, i32) = (0, 0);
 
-            if ray_dir.x < 0.0 { step.0 = -1; side_dist.x = (player.pos.x - map.0 as f32) * delta_dist.x; }
-            else { step.0 = 1; side_dist.x = (map.0 as f32 + 1.0 - player.pos.x) * delta_dist.x; } 
-            if ray_dir.y < 0.0 { step.1 = -1; side_dist.y = (player.pos.y - map.1 as f32) * delta_dist.y; }
-            else { step.1 = 1; side_dist.y = (map.1 as f32 + 1.0 - player.pos.y) * delta_dist.y; }
+            if ray_dir.x < 0.0 { step.0 = -1; side_dist.x = (pos.x - map.0 as f32) * delta_dist.x; }
+            else { step.0 = 1; side_dist.x = (map.0 as f32 + 1.0 - pos.x) * delta_dist.x; }
+            if ray_dir.y < 0.0 { step.1 = -1; side_dist.y = (pos.y - map.1 as f32) * delta_dist.y; }
+            else { step.1 = 1; side_dist.y = (map.1 as f32 + 1.0 - pos.y) * delta_dist.y; }
 
-            Ray { 
-                camera: camera,
+            Ray {
+                pos: pos,
                 ray_dir: ray_dir,
                 map: map,
                 side_dist: side_dist,
@@ -43,10 +50,22 @@ pub mod raycast {
                 step: step,
                 hit: false,
                 side: false,
+                offset: 0.0,
+                texture: 0,
+                height: 1.0,
+                far_dist: 0.0,
             }
         }
 
-        pub fn dda(&mut self, player: &mut Player) {
+        fn left_map(&self, world: &World) -> bool {
+            (self.map.0 < 0 && self.step.0 < 0) || (self.map.0 >= world.height as i32 && self.step.0 > 0) ||
+            (self.map.1 < 0 && self.step.1 < 0) || (self.map.1 >= world.width as i32 && self.step.1 > 0)
+        }
+
+        // Steps to the next wall, calling it again carries on past the last one
+        pub fn dda(&mut self, world: &World) {
+            self.hit = false;
+            self.offset = 0.0;
             while !self.hit {
                 if self.side_dist.x < self.side_dist.y {
                     self.side_dist.x += self.delta_dist.x;
@@ -59,13 +78,90 @@ pub mod raycast {
                     self.map.1 += self.step.1;
                     self.side = true;
                 }
+                // Moving push walls aren't in the map, check them first
+                let map: (i32, i32) = self.map;
+                for wall in world.push_walls.iter().filter(|p| p.covers(map.0, map.1)) {
+                    if self.hit_push_wall(wall) { return; }
+                }
                 // If ray hit wall
-                if player.world.get(self.map.0, self.map.1) > 0 { self.hit = true; }
+                let id: u32 = world.get(self.map.0, self.map.1);
+                if id > 0 {
+                    self.texture = id;
+                    self.height = world.wall_height(self.map.0, self.map.1);
+                    match world.door(self.map.0, self.map.1) {
+                        Some(door) => if self.hit_door(door) { return; },
+                        None => { self.hit = true; self.far_dist = self.side_dist.x.min(self.side_dist.y); }
+                    }
+                }
+                // If ray left the map and is heading away from it
+                else if self.left_map(world) { break; }
             }
             // Calculate distance projected on camera direction
             if !self.side   { self.perp_wall_dist = self.side_dist.x - self.delta_dist.x; }
             else            { self.perp_wall_dist = self.side_dist.y - self.delta_dist.y; }
         }
+
+        // Doors sit in the middle of their cell, the ray only hits the part that hasn't slid away
+        fn hit_door(&mut self, door: &Door) -> bool {
+            let (r, c): (f32, f32) = (self.map.0 as f32, self.map.1 as f32);
+            let (dist, wall_x): (f32, f32) = if !door.side {
+                let dist: f32 = (r + 0.5 - self.pos.x) / self.ray_dir.x;
+                (dist, self.pos.y + dist * self.ray_dir.y - c)
+            } else {
+                let dist: f32 = (c + 0.5 - self.pos.y) / self.ray_dir.y;
+                (dist, self.pos.x + dist * self.ray_dir.x - r)
+            };
+            if !(dist > 0.0 && (door.open..1.0).contains(&wall_x)) { return false; }
+
+            self.perp_wall_dist = dist;
+            self.far_dist = dist;
+            self.side = door.side;
+            self.offset = door.open;
+            self.hit = true;
+            true
+        }
+
+        // Moving push walls are a block between two cells, hit if the ray enters it before leaving this cell
+        fn hit_push_wall(&mut self, wall: &PushWall) -> bool {
+            let min: Vec2 = wall.corner();
+            let max: Vec2 = min + vec2(1.0, 1.0);
+            let slab = |pos: f32, dir: f32, min: f32, max: f32| -> (f32, f32) {
+                if dir == 0.0 {
+                    if pos >= min && pos <= max { (f32::NEG_INFINITY, f32::INFINITY) } else { (f32::INFINITY, f32::NEG_INFINITY) }
+                } else {
+                    let (a, b): (f32, f32) = ((min - pos) / dir, (max - pos) / dir);
+                    (a.min(b), a.max(b))
+                }
+            };
+            let (x_near, x_far): (f32, f32) = slab(self.pos.x, self.ray_dir.x, min.x, max.x);
+            let (y_near, y_far): (f32, f32) = slab(self.pos.y, self.ray_dir.y, min.y, max.y);
+            let (near, far): (f32, f32) = (x_near.max(y_near), x_far.min(y_far));
+            // Entered the block in this cell, a face on the cell edge can round either side of it
+            let enter: f32 = if self.side { self.side_dist.y - self.delta_dist.y } else { self.side_dist.x - self.delta_dist.x };
+            if !(near > 0.0 && near <= far && near >= enter - 1e-4 && near < self.side_dist.x.min(self.side_dist.y)) { return false; }
+
+            self.perp_wall_dist = near;
+            self.far_dist = far;
+            self.side = y_near > x_near;
+            self.offset = if self.side { min.x } else { min.y };
+            self.texture = wall.texture;
+            self.height = wall.height;
+            self.hit = true;
+            true
+        }
+
+        // Column of a texture of the given width the ray hit
+        pub fn tex_x(&self, pos: Vec2, width: u32) -> u32 {
+            let mut wall_x: f32;
+            if !self.side { wall_x = pos.y + self.perp_wall_dist * self.ray_dir.y; }
+            else          { wall_x = pos.x + self.perp_wall_dist * self.ray_dir.x; }
+            wall_x -= self.offset;
+            wall_x -= wall_x.floor();
+
+            let mut tex_x: u32 = ((wall_x * width as f32) as u32).min(width - 1);
+            if !self.side && self.ray_dir.x > 0.0 { tex_x = width - tex_x - 1}
+            if  self.side && self.ray_dir.y < 0.0 { tex_x = width - tex_x - 1}
+            tex_x
+        }
     }
 }
-
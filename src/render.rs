@@ -0,0 +1,565 @@
+pub mod render {
+    use macroquad::prelude::*;
+    use crate::camera::camera::Camera;
+    use crate::display::display::Settings;
+    use crate::map::world::{World, Texture};
+    use crate::raycast::raycast::Ray;
+    use std::sync::mpsc::{channel, Sender, Receiver};
+
+    /* 
+        Draws the world from a camera with macroquad
+    */
+    pub struct Background {
+        pub img: Image,
+        pub texture: Texture2D,
+    }
+    pub struct Renderer {
+        background: Background, // Background image
+        zbuffer: Vec<Vec<Segment>>,  // Walls drawn in each column, for hiding sprites behind
+        sprite_order: Vec<(f32, usize)>, // Sprite distances, furthest first
+        rays: Vec<Vec<Ray>>, // Walls hit in each column
+        spans: Vec<(f32, f32)>, // Visible parts of a sprite column
+        workers: Workers,
+    }
+
+    /*
+        Software renderer
+        Draws the same walls, floors and sprites as Renderer but into an RGBA byte buffer,
+        so frames can be rendered and saved without a window
+    */
+    pub struct SoftwareRenderer {
+        pub width: usize,
+        pub height: usize,
+        pub frame: Vec<u8>, // RGBA, top row first
+        zbuffer: Vec<Vec<Segment>>, // Walls drawn in each column, for hiding sprites behind
+        sprite_order: Vec<(f32, usize)>, // Sprite distances, furthest first
+        rays: Vec<Vec<Ray>>, // Walls hit in each column
+        spans: Vec<(f32, f32)>, // Visible parts of a sprite column
+        workers: Workers,
+    }
+
+    const SPRITE_NEAR: f32 = 0.01; // Sprites nearer the camera plane than this aren't drawn
+
+    // Texel at column x, row y
+    fn texel(t: &Texture, x: u32, y: u32) -> Color {
+        t.texture_data[(y.min(t.height - 1) * t.width + x.min(t.width - 1)) as usize]
+    }
+
+    // Alpha blend a tinted color onto a pixel
+    fn blend(pixel: &mut [u8], color: Color, tint: Color) {
+        let a: f32 = color.a * tint.a;
+        let src: [f32; 3] = [color.r * tint.r, color.g * tint.g, color.b * tint.b];
+        for i in 0..3 {
+            let v: f32 = src[i].clamp(0.0, 1.0) * a * 255.0 + pixel[i] as f32 * (1.0 - a);
+            pixel[i] = v.round() as u8;
+        }
+        pixel[3] = 255;
+    }
+
+    /*
+        Sky panorama, one full turn of the camera scrolls across the whole texture width.
+        The texture covers a screen height above the horizon so it moves with pitch
+    */
+    fn sky_texel(sky: &Texture, ds: &Settings, camera: &Camera, x: usize, y: i32) -> Color {
+        let cam_x: f32 = 2.0 * x as f32 / ds.width - 1.0;
+        let ray_dir: Vec2 = camera.dir + camera.plane * cam_x;
+        let angle: f32 = -ray_dir.y.atan2(ray_dir.x); // Negated so the texture reads left to right
+        let u: u32 = ((angle / std::f32::consts::TAU).rem_euclid(1.0) * sky.width as f32) as u32;
+
+        let horizon: f32 = ds.half_height + camera.pitch;
+        let v: f32 = (y as f32 - (horizon - ds.height)) / ds.height;
+        texel(sky, u, (v.max(0.0) * sky.height as f32) as u32)
+    }
+
+    type Part<'a> = Box<dyn FnOnce() + Send + 'a>; // Some of the chunks of one for_each_chunk call
+    type Job = Part<'static>;
+    type Finished = std::thread::Result<()>; // Err holds the panic of a part that panicked
+
+    /*
+        Threads kept for the life of a renderer so frames don't start new ones.
+        The calling thread does the first part of every job itself, workers are started as more threads are asked for
+    */
+    #[derive(Default)]
+    pub struct Workers {
+        jobs: Vec<Sender<Job>>, // One per worker
+        done: Option<(Sender<Finished>, Receiver<Finished>)>, // Workers report each finished job here
+        handles: Vec<std::thread::JoinHandle<()>>,
+    }
+
+    impl Workers {
+        /*
+            Runs every part, the first on this thread and the rest on workers, and waits for all of them
+            like std::thread::scope. A part that panics is only passed on once every part has finished
+        */
+        fn run(&mut self, parts: Vec<Part>) {
+            let (done_sender, done) = self.done.get_or_insert_with(channel);
+            while self.jobs.len() + 1 < parts.len() {
+                let (sender, receiver): (Sender<Job>, Receiver<Job>) = channel();
+                let done_sender: Sender<Finished> = done_sender.clone();
+                self.handles.push(std::thread::spawn(move || {
+                    for job in receiver { let _ = done_sender.send(std::panic::catch_unwind(std::panic::AssertUnwindSafe(job))); }
+                }));
+                self.jobs.push(sender);
+            }
+
+            /*
+                Nothing may unwind out of here while a sent part is still running, it would free what the part borrows.
+                Parts run on this thread are caught too and their panic held until every sent part has reported back
+            */
+            let run_here = |part: Part| std::panic::catch_unwind(std::panic::AssertUnwindSafe(part));
+            let mut parts = parts.into_iter();
+            let first: Option<Part> = parts.next();
+            let mut result: Finished = Ok(());
+            let mut sent: usize = 0;
+            for (part, worker) in parts.zip(&self.jobs) {
+                // Safe as this doesn't return until the part has run, so nothing it borrows goes away first
+                let part: Job = unsafe { std::mem::transmute::<Part, Job>(part) };
+                match worker.send(part) {
+                    Ok(()) => sent += 1,
+                    Err(e) => { // Worker is gone, run its part here
+                        let finished: Finished = run_here(e.0);
+                        if result.is_ok() { result = finished; }
+                    }
+                }
+            }
+            let finished: Finished = first.map_or(Ok(()), run_here);
+            if result.is_ok() { result = finished; }
+            for _ in 0..sent {
+                // Can't fail while done_sender is held, aborting beats returning with parts still running
+                let finished: Finished = done.recv().unwrap_or_else(|_| std::process::abort());
+                if result.is_ok() { result = finished; }
+            }
+            if let Err(e) = result { std::panic::resume_unwind(e); }
+        }
+
+        // Worker threads started so far, oldest first, the calling thread isn't one of them
+        pub fn threads(&self) -> Vec<std::thread::ThreadId> {
+            self.handles.iter().map(|h| h.thread().id()).collect()
+        }
+    }
+
+    impl Drop for Workers {
+        fn drop(&mut self) {
+            self.jobs.clear(); // Workers stop once their channel closes
+            for handle in self.handles.drain(..) { let _ = handle.join(); }
+        }
+    }
+
+    /*
+        Split data into chunks of chunk_len and run f on each from up to threads threads,
+        f gets the index of the chunk's first item. Chunks don't depend on each other so
+        the result is the same for any number of threads
+    */
+    fn for_each_chunk<T: Send>(workers: &mut Workers, threads: usize, data: &mut [T], chunk_len: usize, f: impl Fn(usize, &mut [T]) + Sync) {
+        if threads <= 1 || data.len() <= chunk_len {
+            for (i, chunk) in data.chunks_mut(chunk_len.max(1)).enumerate() { f(i * chunk_len, chunk); }
+            return;
+        }
+        let per_thread: usize = data.len().div_ceil(chunk_len).div_ceil(threads) * chunk_len;
+        let f = &f;
+        let parts: Vec<Part> = data.chunks_mut(per_thread).enumerate()
+            .map(|(i, part)| Box::new(move || {
+                for (j, chunk) in part.chunks_mut(chunk_len).enumerate() { f(i * per_thread + j * chunk_len, chunk); }
+            }) as Part)
+            .collect();
+        workers.run(parts);
+    }
+
+    /*
+        Cast one ray per screen column, split across ds.threads threads. Each column gets every wall
+        its ray hit nearest first, rays carry on past walls shorter than the tallest in the map
+    */
+    pub fn cast_rays(workers: &mut Workers, world: &World, ds: &Settings, camera: &Camera, columns: &mut Vec<Vec<Ray>>) {
+        columns.resize_with(ds.width as usize, Vec::new);
+        for_each_chunk(workers, ds.threads, columns, 1, |x, column| {
+            let hits: &mut Vec<Ray> = &mut column[0];
+            hits.clear();
+            let mut ray: Ray = Ray::new(x as f32, ds.width, camera);
+            loop {
+                ray.dda(world);
+                if !ray.hit { break; }
+                hits.push(ray);
+                // Nothing behind a wall this tall can show, past the edge of the map there's only more outside
+                if ray.height >= world.max_height || !world.contains(ray.map.0, ray.map.1) { break; }
+            }
+        });
+    }
+
+    // Screen rows a wall covers in a column
+    #[derive(Clone, Copy)]
+    struct Segment {
+        dist: f32,
+        top: f32,
+        bottom: f32,
+    }
+
+    // Screen row of the top edge of a wall of the given height at a distance
+    fn wall_top(ds: &Settings, camera: &Camera, height: f32, dist: f32) -> f32 {
+        let unit: f32 = ds.height / dist;
+        -unit / 2.0 + camera.pitch + ds.half_height + (camera.height / dist) - (height - 1.0) * unit
+    }
+
+    // Top of a wall on screen, the top of its cap if the camera can see over it, and the height of one cell at its distance
+    fn wall_span(ds: &Settings, camera: &Camera, ray: &Ray) -> (f32, f32, f32) {
+        let top: f32 = wall_top(ds, camera, ray.height, ray.perp_wall_dist);
+        (top, wall_top(ds, camera, ray.height, ray.far_dist).min(top), ds.height / ray.perp_wall_dist)
+    }
+
+    // Parts of the rows y0..y1 of a column that aren't hidden by walls closer than dist
+    fn visible(segments: &[Segment], dist: f32, y0: f32, y1: f32, out: &mut Vec<(f32, f32)>) {
+        out.clear();
+        out.push((y0, y1));
+        for s in segments.iter().filter(|s| s.dist < dist) {
+            let n: usize = out.len();
+            for i in 0..n {
+                let (a, b): (f32, f32) = out[i];
+                if a < s.top.min(b) { out.push((a, s.top.min(b))); }
+                if s.bottom.max(a) < b { out.push((s.bottom.max(a), b)); }
+            }
+            out.drain(..n);
+        }
+    }
+
+    /*
+        Floors and ceilings into an RGBA buffer of ds.width * ds.height pixels,
+        rows are split across ds.threads threads
+    */
+    pub fn draw_floor(workers: &mut Workers, world: &World, ds: &Settings, camera: &Camera, pixels: &mut [u8]) {
+        let width = ds.width as usize;
+        for_each_chunk(workers, ds.threads, pixels, width * 4, |i, row| {
+            draw_floor_row(world, ds, camera, (i / (width * 4)) as i32, row);
+        });
+    }
+
+    fn draw_floor_row(world: &World, ds: &Settings, camera: &Camera, y: i32, pixels: &mut [u8]) {
+        let (pos, dir, plane, pitch): (Vec2, Vec2, Vec2, f32) = (camera.pos, camera.dir, camera.plane, camera.pitch);
+        let width = ds.width as usize;
+        let tint: Color = if ds.nightvision { GREEN } else { WHITE };
+
+        let is_floor = y > (ds.half_height + pitch) as i32;
+        let ray_dir_0: Vec2 = vec2(dir.x - plane.x, dir.y - plane.y);
+        let ray_dir_1: Vec2 = vec2(dir.x + plane.x, dir.y + plane.y);
+
+        let p = if is_floor
+                { y - ds.half_height as i32 - pitch as i32 }
+        else    { ds.half_height as i32 - y + pitch as i32 };
+
+        let cam_z = if is_floor
+                { 0.5 * ds.height + camera.height }
+        else    { 0.5 * ds.height - camera.height };
+
+        let row_distance = cam_z / p as f32;
+        let floor_step: Vec2 = vec2(
+            row_distance * (ray_dir_1.x - ray_dir_0.x) / ds.width,
+            row_distance * (ray_dir_1.y - ray_dir_0.y) / ds.width
+        );
+
+        let mut floor: Vec2 = vec2(pos.x + row_distance * ray_dir_0.x, pos.y + row_distance * ray_dir_0.y);
+
+        for x in 0..width {
+            let cell: (i32, i32) = (floor.x.floor() as i32, floor.y.floor() as i32);
+            let surface: Option<usize> = if is_floor { world.floor_texture(cell.0, cell.1, ds.floor_texture) }
+            else { world.ceil_texture(cell.0, cell.1, ds.ceil_texture) };
+            let (fx, fy): (f32, f32) = (floor.x, floor.y);
+            floor.x += floor_step.x; floor.y += floor_step.y;
+
+            let i: usize = x * 4;
+            let t: &Texture = match (surface, &world.sky) {
+                (Some(t), _) => world.texture(t as u32 + 1),
+                (None, Some(sky)) if !is_floor => {
+                    let c: Color = sky_texel(sky, ds, camera, x, y);
+                    pixels[i..i + 4].copy_from_slice(&[(c.r * tint.r * 255.0) as u8, (c.g * tint.g * 255.0) as u8, (c.b * tint.b * 255.0) as u8, 255]);
+                    continue;
+                }
+                (None, _) => { pixels[i..i + 4].copy_from_slice(&[0, 0, 0, 255]); continue; } // No floor, or open sky without a sky texture
+            };
+            // Textures repeat every cell, rows run along x and columns along y
+            let row: u32 = ((t.height as f32 * fx) as i32).rem_euclid(t.height as i32) as u32;
+            let column: u32 = ((t.width as f32 * fy) as i32).rem_euclid(t.width as i32) as u32;
+
+            let mut c: Color = texel(t, column, row);
+            if !ds.nightvision {
+                c = if is_floor { world.floor_shading(c, (ds.height + 50.0) as i32, y, ds.floor_shading_multiplier, ds.dark_shading) }
+                else { world.floor_shading(c, (ds.height + 30.0) as i32, y, ds.ceil_shading_multiplier, ds.dark_shading) };
+            }
+
+            pixels[i] = (c.r * tint.r * 255.0) as u8;
+            pixels[i + 1] = (c.g * tint.g * 255.0) as u8;
+            pixels[i + 2] = (c.b * tint.b * 255.0) as u8;
+            pixels[i + 3] = 255;
+        }
+    }
+
+    // Fill order with (distance squared, index) of every sprite, furthest first
+    fn sort_sprites(world: &World, pos: Vec2, order: &mut Vec<(f32, usize)>) {
+        order.clear();
+        for (i, s) in world.sprite_map.iter().enumerate() {
+            order.push(((pos.x - s.x) * (pos.x - s.x) + (pos.y - s.y) * (pos.y - s.y), i));
+        }
+        order.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap());
+    }
+
+    impl Renderer {
+        pub fn new(ds: &Settings) -> Renderer {
+            // Background image used to draw the floors
+            let img = Image::gen_image_color(ds.width as u16, ds.height as u16, BLACK);
+            let background: Background = Background { 
+                texture: Texture2D::from_image(&img),
+                img: img,
+            };
+
+            Renderer { 
+                background: background,
+                zbuffer: vec![Vec::new(); ds.width as usize],
+                sprite_order: Vec::new(),
+                rays: Vec::new(),
+                spans: Vec::new(),
+                workers: Workers::default(),
+            }
+        }
+
+        pub fn draw(&mut self, world: &World, camera: &Camera, ds: &Settings) {
+            if ds.draw_floors { self.draw_floor(world, camera, ds); }
+            if ds.draw_walls { self.raycast(world, camera, ds); } // Walls are drawn here
+            if ds.draw_sprites { self.draw_sprites(world, camera, ds); }
+        }
+
+        /* 
+            Floors and ceilings are written straight into the background image bytes,
+            then copied to the same GPU texture every frame so nothing is allocated per frame
+        */
+        pub fn draw_floor(&mut self, world: &World, camera: &Camera, ds: &Settings) {      
+            draw_floor(&mut self.workers, world, ds, camera, &mut self.background.img.bytes);
+            self.background.texture.update(&self.background.img); 
+            draw_texture(self.background.texture, 0., 0., WHITE);
+        }
+
+        fn draw_walls(&mut self, world: &World, camera: &Camera, ds: &Settings, ray: Ray, x: f32) {
+            let c: Color = if ds.nightvision { GREEN } 
+            else { 
+                world.wall_shading(
+                    ray.side, 
+                    ray.perp_wall_dist, 
+                    ds.shadows, 
+                    ds.dark_shading, 
+                    ds.wall_shading_multiplier
+                ) 
+            };
+            let t: &Texture = world.texture(ray.texture);
+            let rect: Rect = world.atlas.wall(ray.texture);
+        
+            let (draw_start, cap, unit): (f32, f32, f32) = wall_span(ds, camera, &ray);
+            // Store the wall strip for spritecast
+            self.zbuffer[x as usize].push(Segment { dist: ray.perp_wall_dist, top: cap, bottom: draw_start + ray.height * unit });
+            if cap < draw_start { // Top of a wall the camera is above
+                let a: Color = t.average;
+                draw_rectangle(x, cap, 1.0, draw_start - cap, Color::new(a.r * c.r, a.g * c.g, a.b * c.b, 1.0));
+            }
+
+            let tex_x: u32 = ray.tex_x(camera.pos, t.width);
+
+            // Walls taller or shorter than a cell repeat or cut off the texture, one cell at a time
+            let mut k: f32 = 0.0;
+            while k < ray.height {
+                let piece: f32 = (ray.height - k).min(1.0);
+                draw_texture_ex(
+                    world.atlas.texture,
+                    x,
+                    draw_start + k * unit,
+                    c,
+                    DrawTextureParams {
+                        dest_size: Some(vec2(1.0, piece * unit)), 
+                        source: Some(Rect::new(rect.x + tex_x as f32, rect.y, 1.0, piece * rect.h)), // Part of the atlas to draw
+                        ..Default::default()
+                    }
+                );
+                k += 1.0;
+            }
+        }
+
+        pub fn draw_sprites(&mut self, world: &World, camera: &Camera, ds: &Settings) {
+            let (pos, dir, plane): (Vec2, Vec2, Vec2) = (camera.pos, camera.dir, camera.plane);
+            sort_sprites(world, pos, &mut self.sprite_order);
+
+            for &(dist, i) in &self.sprite_order {
+                let s = &world.sprite_map[i];
+                let c: Color = if ds.nightvision { GREEN } 
+                else { world.sprite_shading(dist, ds.dark_shading, ds.sprite_shading_multiplier) };
+
+                let sprite: Vec2 = vec2(s.x - pos.x, s.y - pos.y);
+                let inv_det: f32 = 1.0 / (plane.x * dir.y - dir.x * plane.y);
+                let transform: Vec2 = vec2(
+                    inv_det * (dir.y * sprite.x - dir.x * sprite.y), 
+                    inv_det * (-plane.y * sprite.x + plane.x * sprite.y)
+                );
+                if transform.y < SPRITE_NEAR { continue; } // Behind the camera, or so close the sizes overflow
+
+                let v_move_screen = (camera.pitch + camera.height / transform.y) as i32;
+                let sprite_screen: i32 = (ds.half_width * (1.0 + transform.x / transform.y)) as i32;
+                let sprite_height: i32 = (ds.height / transform.y) as i32;
+                let half_sprite_height: (i32, i32) = (-sprite_height / 2, sprite_height / 2);
+
+                let draw_start_y: i32 = half_sprite_height.0 + ds.half_height as i32 + v_move_screen;
+                let draw_end_y: i32 = half_sprite_height.1 + ds.half_height as i32 + v_move_screen;
+                let rect: Rect = world.atlas.sprite(s.texture);
+                // Sprites keep the aspect ratio of their texture
+                let sprite_width: i32 = (sprite_height as i64 * rect.w as i64 / rect.h as i64) as i32;
+                let draw_start_x: i32 = -sprite_width / 2 + sprite_screen;
+                let draw_end_x: i32 = sprite_width / 2 + sprite_screen;
+
+                let (y0, y1): (f32, f32) = (draw_start_y as f32, draw_end_y as f32);
+                if y1 <= y0 || sprite_width <= 0 { continue; }
+                for x in draw_start_x.max(1)..draw_end_x.min(ds.width as i32) {
+                    let tex_x: i32 = ((x - draw_start_x) as i64 * rect.w as i64 / sprite_width as i64) as i32;
+                    visible(&self.zbuffer[x as usize], transform.y, y0, y1, &mut self.spans);
+
+                    for &(a, b) in &self.spans {
+                        draw_texture_ex(
+                            world.atlas.texture,
+                            x as f32,
+                            a,
+                            c,
+                            DrawTextureParams {
+                                dest_size: Some(vec2(1.0, b - a)), 
+                                source: Some(Rect::new(rect.x + tex_x as f32, rect.y + (a - y0) / (y1 - y0) * rect.h, 1.0, (b - a) / (y1 - y0) * rect.h)), // Part of the atlas to draw
+                                ..Default::default()
+                            }
+                        );
+                    }
+                }
+            }
+        }
+
+        pub fn raycast(&mut self, world: &World, camera: &Camera, ds: &Settings) { 
+            let mut rays: Vec<Vec<Ray>> = std::mem::take(&mut self.rays);
+            cast_rays(&mut self.workers, world, ds, camera, &mut rays);
+            self.zbuffer.resize_with(rays.len(), Vec::new);
+            for (x, hits) in rays.iter().enumerate() {
+                self.zbuffer[x].clear();
+                // Furthest first so nearer walls are drawn over the ones behind them
+                for ray in hits.iter().rev() { self.draw_walls(world, camera, ds, *ray, x as f32); }
+            }
+            self.rays = rays;
+        }
+    }
+
+    impl SoftwareRenderer {
+        pub fn new(ds: &Settings) -> SoftwareRenderer {
+            SoftwareRenderer {
+                width: ds.width as usize,
+                height: ds.height as usize,
+                frame: vec![0; ds.width as usize * ds.height as usize * 4],
+                zbuffer: vec![Vec::new(); ds.width as usize],
+                sprite_order: Vec::new(),
+                rays: Vec::new(),
+                spans: Vec::new(),
+                workers: Workers::default(),
+            }
+        }
+
+        // Threads the renderer draws with, kept from frame to frame
+        pub fn workers(&self) -> &Workers {
+            &self.workers
+        }
+
+        pub fn render(&mut self, world: &World, ds: &Settings, camera: &Camera) {
+            self.width = ds.width as usize;
+            self.height = ds.height as usize;
+            self.frame.clear();
+            self.frame.resize(self.width * self.height * 4, 0);
+            for p in self.frame.chunks_exact_mut(4) { p[3] = 255; }
+            self.zbuffer.resize_with(self.width, Vec::new);
+
+            if ds.draw_floors { draw_floor(&mut self.workers, world, ds, camera, &mut self.frame); }
+            if ds.draw_walls { self.draw_walls(world, ds, camera); }
+            if ds.draw_sprites { self.draw_sprites(world, ds, camera); }
+        }
+
+        fn draw_walls(&mut self, world: &World, ds: &Settings, camera: &Camera) {
+            cast_rays(&mut self.workers, world, ds, camera, &mut self.rays);
+            for x in 0..self.width {
+                self.zbuffer[x].clear();
+                // Furthest first so nearer walls are drawn over the ones behind them
+                for ray in self.rays[x].iter().rev() {
+                    let c: Color = if ds.nightvision { GREEN }
+                    else { world.wall_shading(ray.side, ray.perp_wall_dist, ds.shadows, ds.dark_shading, ds.wall_shading_multiplier) };
+                    let t: &Texture = world.texture(ray.texture);
+
+                    let (draw_start, cap, unit): (f32, f32, f32) = wall_span(ds, camera, ray);
+                    let draw_end: f32 = draw_start + ray.height * unit;
+                    self.zbuffer[x].push(Segment { dist: ray.perp_wall_dist, top: cap, bottom: draw_end });
+
+                    // Top of a wall the camera is above
+                    for y in cap.round().clamp(0.0, self.height as f32) as usize..draw_start.round().clamp(0.0, self.height as f32) as usize {
+                        let i: usize = (y * self.width + x) * 4;
+                        blend(&mut self.frame[i..i + 4], t.average, c);
+                    }
+
+                    let tex_x: u32 = ray.tex_x(camera.pos, t.width);
+                    let y_start: usize = draw_start.round().clamp(0.0, self.height as f32) as usize;
+                    let y_end: usize = draw_end.round().clamp(0.0, self.height as f32) as usize;
+                    let last_tile: f32 = (ray.height.ceil() - 1.0).max(0.0);
+                    for y in y_start..y_end {
+                        // The texture repeats once per cell of height, starting from the top
+                        let v: f32 = (y as f32 + 0.5 - draw_start) / unit;
+                        let tex_y: u32 = ((v - v.floor().clamp(0.0, last_tile)) * t.height as f32) as u32;
+                        let i: usize = (y * self.width + x) * 4;
+                        blend(&mut self.frame[i..i + 4], texel(t, tex_x, tex_y), c);
+                    }
+                }
+            }
+        }
+
+        fn draw_sprites(&mut self, world: &World, ds: &Settings, camera: &Camera) {
+            let (pos, dir, plane): (Vec2, Vec2, Vec2) = (camera.pos, camera.dir, camera.plane);
+            sort_sprites(world, pos, &mut self.sprite_order);
+
+            let sprite_order: Vec<(f32, usize)> = std::mem::take(&mut self.sprite_order);
+            for &(dist, i) in &sprite_order {
+                let s = &world.sprite_map[i];
+                let t: &Texture = &world.sprite_textures[s.texture];
+                let c: Color = if ds.nightvision { GREEN }
+                else { world.sprite_shading(dist, ds.dark_shading, ds.sprite_shading_multiplier) };
+
+                let sprite: Vec2 = vec2(s.x - pos.x, s.y - pos.y);
+                let inv_det: f32 = 1.0 / (plane.x * dir.y - dir.x * plane.y);
+                let transform: Vec2 = vec2(
+                    inv_det * (dir.y * sprite.x - dir.x * sprite.y),
+                    inv_det * (-plane.y * sprite.x + plane.x * sprite.y)
+                );
+                if transform.y < SPRITE_NEAR { continue; } // Behind the camera, or so close the sizes overflow
+
+                let v_move_screen = (camera.pitch + camera.height / transform.y) as i32;
+                let sprite_screen: i32 = (ds.half_width * (1.0 + transform.x / transform.y)) as i32;
+                let sprite_height: i32 = (ds.height / transform.y) as i32;
+                let half_sprite_height: (i32, i32) = (-sprite_height / 2, sprite_height / 2);
+
+                let draw_start_y: i32 = half_sprite_height.0 + ds.half_height as i32 + v_move_screen;
+                let draw_end_y: i32 = half_sprite_height.1 + ds.half_height as i32 + v_move_screen;
+                // Sprites keep the aspect ratio of their texture
+                let sprite_width: i32 = (sprite_height as i64 * t.width as i64 / t.height as i64) as i32;
+                let draw_start_x: i32 = -sprite_width / 2 + sprite_screen;
+                let draw_end_x: i32 = sprite_width / 2 + sprite_screen;
+                if draw_end_y <= draw_start_y || sprite_width <= 0 { continue; }
+
+                for x in draw_start_x.max(1)..draw_end_x.min(self.width as i32) {
+                    let tex_x: u32 = ((x - draw_start_x) as i64 * t.width as i64 / sprite_width as i64) as u32;
+                    visible(&self.zbuffer[x as usize], transform.y, draw_start_y as f32, draw_end_y as f32, &mut self.spans);
+
+                    for &(a, b) in &self.spans {
+                        for y in (a.round() as i32).max(0)..(b.round() as i32).min(self.height as i32) {
+                            let tex_y: u32 = ((y - draw_start_y) as i64 * t.height as i64 / (draw_end_y - draw_start_y) as i64) as u32;
+                            let i: usize = (y as usize * self.width + x as usize) * 4;
+                            blend(&mut self.frame[i..i + 4], texel(t, tex_x, tex_y), c);
+                        }
+                    }
+                }
+            }
+            self.sprite_order = sprite_order;
+        }
+
+        pub fn save_png(&self, path: &str) -> image::ImageResult<()> {
+            image::save_buffer(path, &self.frame, self.width as u32, self.height as u32, image::ColorType::Rgba8)
+        }
+    }
+}
@@ -0,0 +1,30 @@
+/*
+    Asset loading errors and the missing texture placeholder
+*/
+use raycast::{AssetError, AssetSource, LevelError, Texture, World};
+
+const LEVEL: &str = "tests/golden/missing.map";
+
+#[test]
+fn missing_texture_fails_the_level() {
+    match World::load_headless(LEVEL) {
+        Err(LevelError::Asset(AssetError::Read(path, _))) => assert_eq!(path, "tests/golden/does_not_exist.png"),
+        Err(e) => panic!("wrong error: {}", e),
+        Ok(_) => panic!("loaded a level with a missing texture"),
+    }
+}
+
+#[test]
+fn placeholders_stand_in() {
+    let (world, missing): (World, Vec<AssetError>) = World::load_with_placeholders(&AssetSource::default(), LEVEL).unwrap();
+    let errors: Vec<String> = missing.iter().map(|e| e.to_string()).collect();
+    assert_eq!(errors.len(), 2, "{:?}", errors);
+    assert!(errors[0].starts_with("couldn't read tests/golden/does_not_exist.png: "), "{}", errors[0]);
+    assert!(errors[1].starts_with("couldn't decode tests/golden/broken.png: "), "{}", errors[1]);
+
+    // The good texture still loads, the others are the checkerboard
+    let placeholder: Texture = Texture::placeholder();
+    assert_ne!(world.texture(1).texture_data, placeholder.texture_data);
+    assert_eq!(world.texture(2).texture_data, placeholder.texture_data);
+    assert_eq!(world.texture(3).texture_data, placeholder.texture_data);
+}
@@ -0,0 +1,67 @@
+/*
+    Packing wall and sprite textures of any size into one atlas image
+*/
+use macroquad::prelude::*;
+use raycast::{Atlas, AssetError, Texture, TextureRegistry, World};
+
+// Every texel of t is in the atlas image at rect
+fn contains(atlas: &Atlas, rect: Rect, t: &Texture) -> bool {
+    (0..t.height).all(|y| (0..t.width).all(|x| {
+        let i: usize = (((rect.y as u32 + y) * atlas.image.width as u32 + rect.x as u32 + x) * 4) as usize;
+        let c: Color = t.texture_data[(y * t.width + x) as usize];
+        atlas.image.bytes[i..i + 4] == [c.r, c.g, c.b, c.a].map(|v| (v * 255.0).round() as u8)
+    }))
+}
+
+#[test]
+fn packs_textures_of_any_size() {
+    let world: World = World::load_headless("tests/golden/sizes.map").unwrap();
+    let atlas: Atlas = Atlas::new(&world.textures, &world.sprite_textures, 2048).unwrap();
+
+    let rects: Vec<(Rect, &Texture)> = vec![
+        (atlas.wall(1), world.texture(1)),
+        (atlas.wall(2), world.texture(2)),
+        (atlas.sprite(0), &world.sprite_textures[0]),
+    ];
+    assert_eq!((rects[0].0.w, rects[0].0.h), (48.0, 20.0));
+    assert_eq!((rects[2].0.w, rects[2].0.h), (24.0, 40.0));
+    for (i, &(rect, t)) in rects.iter().enumerate() {
+        assert!(rect.x >= 1.0 && rect.y >= 1.0, "texture {} has no padding", i);
+        assert!(rect.right() < atlas.image.width as f32 && rect.bottom() < atlas.image.height as f32, "texture {} is outside the atlas", i);
+        assert!(contains(&atlas, rect, t), "texture {} wasn't copied into the atlas", i);
+        // Padding around one texture can't reach into another
+        for &(other, _) in &rects[i + 1..] {
+            let apart: bool = rect.right() + 2.0 <= other.x || other.right() + 2.0 <= rect.x
+                || rect.bottom() + 2.0 <= other.y || other.bottom() + 2.0 <= rect.y;
+            assert!(apart, "texture {} overlaps another", i);
+        }
+    }
+}
+
+#[test]
+fn empty_atlas() {
+    let world: World = World::load_headless("tests/golden/sizes.map").unwrap();
+    let atlas: Atlas = Atlas::new(&Default::default(), &[], 2048).unwrap();
+    assert_eq!((atlas.image.width, atlas.image.height), (0, 0));
+    assert!(!atlas.uploaded() && !world.atlas.uploaded());
+}
+
+#[test]
+fn atlas_too_big() {
+    let world: World = World::load_headless("tests/golden/sizes.map").unwrap();
+    match Atlas::new(&world.textures, &world.sprite_textures, 32) {
+        Err(AssetError::AtlasSize(size, 32)) => assert!(size.0 > 32 || size.1 > 32),
+        _ => panic!("expected the atlas to be too big"),
+    }
+
+    // Images can't be over u16::MAX wide whatever the GPU takes
+    let mut wide: Texture = world.texture(1).clone();
+    (wide.width, wide.height) = (70000, 1);
+    wide.texture_data = vec![WHITE; 70000];
+    let mut textures: TextureRegistry = TextureRegistry::default();
+    textures.add("wide", wide);
+    match Atlas::new(&textures, &[], u32::MAX) {
+        Err(e @ AssetError::AtlasSize(_, 65535)) => assert_eq!(e.to_string(), "textures need a 70002x3 atlas, the largest allowed is 65535x65535"),
+        _ => panic!("expected the atlas to be too wide"),
+    }
+}
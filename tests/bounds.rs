@@ -0,0 +1,83 @@
+/*
+    Non-square maps with open edges, rays and the player can leave through the gaps
+*/
+use macroquad::prelude::*;
+use raycast::{AssetSource, Camera, Ray, World};
+
+// 3 rows by 6 columns with no wall around it, nothing is outside
+const OPEN: &str = "\
+[level]
+manifest src/assets/manifest.txt
+[textures]
+1 red_brick
+[spawn]
+pos 1.5 2.5
+[map]
+0 1 0 0 0 0
+0 0 0 0 0 1
+0 0 0 0 0 0
+";
+
+// Levels are read from memory, the manifest and images from the repository
+fn load(source: &'static str) -> World {
+    let files: &'static [(&'static str, &'static [u8])] = Box::leak(Box::new([("test.map", source.as_bytes())]));
+    let assets: AssetSource = AssetSource::Layers(vec![AssetSource::Embedded(files), AssetSource::default()]);
+    World::load_with_placeholders(&assets, "test.map").unwrap().0
+}
+
+fn cast(world: &World, dir: Vec2) -> Ray {
+    let camera: Camera = Camera::new(world.spawn.pos, dir.normalize());
+    let mut ray: Ray = Ray::new(50.0, 100.0, &camera);
+    ray.dda(world);
+    ray
+}
+
+#[test]
+fn rays_leave_through_open_edges() {
+    let world: World = load(OPEN);
+    assert_eq!((world.width, world.height), (6, 3));
+
+    // Along the short axis out past row 2, and the long axis out past column 0
+    let ray: Ray = cast(&world, vec2(1.0, 0.0));
+    assert!(!ray.hit && ray.map.0 == 3, "{:?}", ray.map);
+    let ray: Ray = cast(&world, vec2(0.0, -1.0));
+    assert!(!ray.hit && ray.map.1 == -1, "{:?}", ray.map);
+    let ray: Ray = cast(&world, vec2(0.0, 1.0));
+    assert!(ray.hit && ray.map == (1, 5) && (ray.perp_wall_dist - 2.5).abs() < 1e-4, "{:?}", ray.map);
+
+    // Every direction either hits a wall in the map or stops just outside it
+    for i in 0..360 {
+        let angle: f32 = (i as f32).to_radians();
+        let mut ray: Ray = cast(&world, vec2(angle.cos(), angle.sin()));
+        if ray.hit {
+            assert!(world.contains(ray.map.0, ray.map.1) && world.get(ray.map.0, ray.map.1) > 0, "{}: {:?}", i, ray.map);
+            ray.dda(&world); // Carries on past the wall
+        }
+        if !ray.hit {
+            assert!(!world.contains(ray.map.0, ray.map.1), "{}: stopped inside at {:?}", i, ray.map);
+            assert!((-1..=3).contains(&ray.map.0) && (-1..=6).contains(&ray.map.1), "{}: went past the edge to {:?}", i, ray.map);
+        }
+    }
+}
+
+#[test]
+fn outside_the_edges() {
+    let world: World = load(OPEN);
+    for (r, c) in [(-1, 0), (3, 0), (0, -1), (0, 6), (3, 6), (2, 6), (3, 5)] {
+        assert!(!world.contains(r, c), "({}, {})", r, c);
+        assert_eq!(world.get(r, c), 0);
+        assert!(!world.solid(r, c), "({}, {})", r, c);
+    }
+    assert!(world.contains(2, 5) && world.contains(0, 0));
+    assert!(world.solid(1, 5) && !world.solid(2, 5));
+
+    // Off the map is a wall of the outside texture on every side
+    let walled: World = load(Box::leak(OPEN.replace("[textures]", "outside 1\n[textures]").into_boxed_str()));
+    for (r, c) in [(-1, 0), (3, 0), (0, -1), (0, 6), (-1, -1), (3, 6)] {
+        assert_eq!(walled.get(r, c), 1);
+        assert!(walled.solid(r, c), "({}, {})", r, c);
+    }
+    assert_eq!(walled.get(2, 5), 0);
+    let ray: Ray = cast(&walled, vec2(1.0, 0.0));
+    assert!(ray.hit && ray.map == (3, 2) && ray.texture == 1, "{:?}", ray.map);
+}
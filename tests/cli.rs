@@ -0,0 +1,73 @@
+/*
+    Command line options
+*/
+use raycast::cli::cli::Options;
+
+fn parse(args: &str) -> Result<Options, String> {
+    Options::parse(args.split_whitespace().map(String::from))
+}
+
+#[test]
+fn defaults() {
+    assert_eq!(parse("").unwrap(), Options::default());
+}
+
+#[test]
+fn reads_every_option() {
+    let options: Options = parse("--level a.map --assets game.pak --size 800x600 --fullscreen --resolution 320x200 --settings b.cfg --headless --frames 12 --out shots").unwrap();
+    assert_eq!(options, Options {
+        level: String::from("a.map"),
+        assets: String::from("game.pak"),
+        size: (800, 600),
+        fullscreen: true,
+        resolution: Some((320, 200)),
+        settings: String::from("b.cfg"),
+        headless: true,
+        frames: 12,
+        out: String::from("shots"),
+        help: false,
+    });
+}
+
+#[test]
+fn rejects_bad_options() {
+    assert_eq!(parse("--size 800").unwrap_err(), "--size expected <width>x<height> like 640x480, got '800'");
+    assert_eq!(parse("--resolution 0x200").unwrap_err(), "--resolution expected <width>x<height> like 640x480, got '0x200'");
+    assert_eq!(parse("--frames 0").unwrap_err(), "--frames expected a number above 0, got '0'");
+    assert_eq!(parse("--level").unwrap_err(), "--level needs a value");
+    assert_eq!(parse("--wat").unwrap_err(), "unknown option '--wat'");
+}
+
+// Runs the game headless for one frame and loads it
+fn headless_frame(name: &str, args: &[&str]) -> image::RgbaImage {
+    let out: std::path::PathBuf = std::env::temp_dir().join(format!("raycast_cli_{}", name));
+    let _ = std::fs::remove_dir_all(&out);
+    let status = std::process::Command::new(env!("CARGO_BIN_EXE_raycast"))
+        .args(["--headless", "--frames", "1", "--settings", "does_not_exist.cfg", "--out"]).arg(&out).args(args)
+        .stdout(std::process::Stdio::null())
+        .status().unwrap();
+    assert!(status.success());
+    image::open(out.join("frame_0000.png")).unwrap().to_rgba8()
+}
+
+#[test]
+fn headless_uses_the_resolution() {
+    // Without --resolution the settings default of 640x480 is drawn, then scaled to the window size
+    let frame: image::RgbaImage = headless_frame("default", &["--size", "320x240"]);
+    assert_eq!(frame.dimensions(), (320, 240));
+
+    // Every 4x4 block of the window is one pixel at 80x60
+    let frame: image::RgbaImage = headless_frame("low", &["--size", "320x240", "--resolution", "80x60"]);
+    assert_eq!(frame.dimensions(), (320, 240));
+    for (x, y, pixel) in frame.enumerate_pixels() {
+        assert_eq!(pixel, frame.get_pixel(x / 4 * 4, y / 4 * 4), "({}, {})", x, y);
+    }
+}
+
+#[test]
+fn levels_with_fewer_textures_than_the_defaults() {
+    for level in ["tests/golden/one_texture.map", "tests/golden/no_textures.map"] {
+        let frame: image::RgbaImage = headless_frame("textures", &["--size", "64x48", "--resolution", "64x48", "--level", level]);
+        assert_eq!(frame.dimensions(), (64, 48));
+    }
+}
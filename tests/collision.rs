@@ -0,0 +1,52 @@
+/*
+    Player collision against the walls of tests/golden/level.map
+*/
+use macroquad::prelude::*;
+use raycast::{Player, World};
+
+const RADIUS: f32 = 0.2;
+
+fn player_at(world: &World, x: f32, y: f32) -> Player {
+    let mut player: Player = Player::new(world);
+    player.camera.pos = vec2(x, y);
+    player
+}
+
+#[test]
+fn stops_short_of_walls() {
+    let world: World = World::load_headless("tests/golden/level.map").unwrap();
+    let mut player: Player = player_at(&world, 1.5, 1.5);
+    for _ in 0..100 { player.move_by(&world, vec2(-0.05, 0.0), RADIUS); }
+    // The wall is row 0, the player keeps at least its radius away from it
+    assert!(player.camera.pos.x >= 1.0 + RADIUS, "walked into the wall, x = {}", player.camera.pos.x);
+    assert!(player.camera.pos.x < 1.0 + RADIUS + 0.05, "stopped too early, x = {}", player.camera.pos.x);
+}
+
+#[test]
+fn slides_along_walls() {
+    let world: World = World::load_headless("tests/golden/level.map").unwrap();
+    let mut player: Player = player_at(&world, 1.5, 1.5);
+    for _ in 0..20 { player.move_by(&world, vec2(-0.05, 0.05), RADIUS); }
+    // Blocked going into the wall but still moving along it
+    assert!(player.camera.pos.x >= 1.0 + RADIUS);
+    assert!((player.camera.pos.y - 2.5).abs() < 1e-4, "didn't slide, y = {}", player.camera.pos.y);
+}
+
+#[test]
+fn corners_block_diagonals() {
+    let world: World = World::load_headless("tests/golden/level.map").unwrap();
+    let mut player: Player = player_at(&world, 1.5, 1.5);
+    for _ in 0..100 { player.move_by(&world, vec2(-0.05, -0.05), RADIUS); }
+    assert!(player.camera.pos.x >= 1.0 + RADIUS && player.camera.pos.y >= 1.0 + RADIUS);
+}
+
+#[test]
+fn can_back_out_of_walls() {
+    let world: World = World::load_headless("tests/golden/level.map").unwrap();
+    // Overlapping the wall already, like after making the radius bigger
+    let mut player: Player = player_at(&world, 1.1, 1.5);
+    player.move_by(&world, vec2(-0.05, 0.0), RADIUS);
+    assert!((player.camera.pos.x - 1.1).abs() < 1e-4, "went further into the wall, x = {}", player.camera.pos.x);
+    player.move_by(&world, vec2(0.1, 0.0), RADIUS);
+    assert!((player.camera.pos.x - 1.2).abs() < 1e-4, "couldn't back out, x = {}", player.camera.pos.x);
+}
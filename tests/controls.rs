@@ -0,0 +1,57 @@
+/*
+    Controls config parsing
+*/
+use macroquad::prelude::*;
+use raycast::{Action, Controls, Stick};
+
+#[test]
+fn defaults_round_trip() {
+    let defaults: Controls = Controls::default();
+    let (parsed, warnings): (Controls, Vec<String>) = Controls::parse(&defaults.to_config());
+    assert!(warnings.is_empty(), "{:?}", warnings);
+    for action in Action::ALL {
+        assert_eq!(parsed.keys(action), defaults.keys(action), "{:?}", action);
+    }
+}
+
+#[test]
+fn rebinds_and_keeps_the_rest() {
+    let (controls, warnings): (Controls, Vec<String>) = Controls::parse("# Comment\nforward = up, I  # arrow or I\n\nquit =\n");
+    assert!(warnings.is_empty(), "{:?}", warnings);
+    assert_eq!(controls.keys(Action::Forward), &[KeyCode::Up, KeyCode::I]);
+    assert!(controls.keys(Action::Quit).is_empty());
+    assert_eq!(controls.keys(Action::Back), Controls::default().keys(Action::Back));
+}
+
+#[test]
+fn warns_about_bad_lines() {
+    let (controls, warnings): (Controls, Vec<String>) = Controls::parse("jump = Space\nforward = W, Hyperspace\nback S\n");
+    assert_eq!(warnings, vec![
+        String::from("line 1: unknown action 'jump'"),
+        String::from("line 2: unknown key 'Hyperspace'"),
+        String::from("line 3: expected '<action> = <key>, ...'"),
+    ]);
+    assert_eq!(controls.keys(Action::Forward), &[KeyCode::W]);
+}
+
+#[test]
+fn rebinding_swallows_its_key() {
+    let mut controls: Controls = Controls::default();
+    assert!(!controls.rebind(Some(KeyCode::J)), "swallowed a key without rebinding");
+
+    // Escape cancels without quitting in the same frame
+    controls.rebinding = Some(Action::Interact);
+    assert!(!controls.rebind(None));
+    assert!(controls.rebind(Some(KeyCode::Escape)));
+    assert_eq!(controls.rebinding, None);
+    assert_eq!(controls.keys(Action::Interact), Controls::default().keys(Action::Interact));
+    assert!(!controls.pressed(Action::Quit) && !controls.down(Action::Quit));
+
+    // The new key is bound but doesn't interact until the next frame
+    controls.rebinding = Some(Action::Interact);
+    assert!(controls.rebind(Some(KeyCode::J)));
+    assert_eq!(controls.keys(Action::Interact), &[KeyCode::J]);
+    assert!(!controls.pressed(Action::Interact));
+    assert_eq!(controls.stick(Stick::Left, 0.0), Vec2::ZERO);
+    assert!(controls.take_changed());
+}
@@ -0,0 +1,136 @@
+/*
+    Sliding doors in tests/golden/level.map, the door at (4, 4) closes the gap between rows 3 and 5
+*/
+use macroquad::prelude::*;
+use raycast::{Camera, DoorState, Player, Ray, World};
+
+const DOOR: (i32, i32) = (4, 4);
+const RADIUS: f32 = 0.2;
+const AWAY: Vec2 = Vec2::new(2.5, 4.5); // Player position clear of the doorway
+
+fn state(world: &World) -> (DoorState, f32) {
+    let door = world.door(DOOR.0, DOOR.1).unwrap();
+    (door.state, door.open)
+}
+
+fn close(a: (DoorState, f32), b: (DoorState, f32)) -> bool {
+    a.0 == b.0 && (a.1 - b.1).abs() < 1e-4
+}
+
+// Open the door all the way from a distance
+fn opened() -> World {
+    let mut world: World = World::load_headless("tests/golden/level.map").unwrap();
+    world.use_door(DOOR, AWAY, RADIUS);
+    world.update(1.0, AWAY, RADIUS);
+    world
+}
+
+#[test]
+fn opens_waits_and_closes() {
+    let mut world: World = World::load_headless("tests/golden/level.map").unwrap();
+    assert!(close(state(&world), (DoorState::Closed, 0.0)));
+    world.update(10.0, AWAY, RADIUS);
+    assert!(close(state(&world), (DoorState::Closed, 0.0)), "opened by itself");
+
+    world.use_door(DOOR, AWAY, RADIUS);
+    world.update(0.5, AWAY, RADIUS);
+    assert!(close(state(&world), (DoorState::Opening, 0.5)));
+    world.update(0.5, AWAY, RADIUS);
+    assert!(close(state(&world), (DoorState::Open, 1.0)));
+
+    // Stays open for DOOR_WAIT seconds, then slides shut
+    world.update(2.9, AWAY, RADIUS);
+    assert!(close(state(&world), (DoorState::Open, 1.0)));
+    world.update(0.2, AWAY, RADIUS);
+    assert_eq!(state(&world).0, DoorState::Closing);
+    world.update(0.5, AWAY, RADIUS);
+    assert!(close(state(&world), (DoorState::Closing, 0.5)));
+    world.update(0.5, AWAY, RADIUS);
+    assert!(close(state(&world), (DoorState::Closed, 0.0)));
+}
+
+#[test]
+fn using_a_door_again() {
+    let mut world: World = opened();
+    world.use_door(DOOR, AWAY, RADIUS);
+    world.update(0.25, AWAY, RADIUS);
+    assert!(close(state(&world), (DoorState::Closing, 0.75)));
+    // Using it while it closes opens it again from where it got to
+    world.use_door(DOOR, AWAY, RADIUS);
+    world.update(0.25, AWAY, RADIUS);
+    assert!(close(state(&world), (DoorState::Open, 1.0)));
+}
+
+#[test]
+fn wont_close_on_the_player() {
+    let mut world: World = opened();
+    let doorway: Vec2 = vec2(4.5, 4.5);
+    world.update(10.0, doorway, RADIUS);
+    assert!(close(state(&world), (DoorState::Open, 1.0)), "closed with the player in the doorway");
+    world.use_door(DOOR, doorway, RADIUS);
+    assert_eq!(state(&world).0, DoorState::Open, "shut on the player when used");
+
+    // Walking in while it closes opens it back up
+    world.update(0.0, AWAY, RADIUS);
+    assert_eq!(state(&world).0, DoorState::Closing);
+    world.update(0.5, AWAY, RADIUS);
+    let near: Vec2 = vec2(3.9, 4.5); // Footprint reaches into the door cell
+    world.update(0.1, near, RADIUS);
+    assert_eq!(state(&world).0, DoorState::Opening);
+    world.update(0.5, near, RADIUS);
+    assert!(close(state(&world), (DoorState::Open, 1.0)));
+}
+
+// How far a player walking at the door from row 3 gets
+fn walk_in(world: &World) -> f32 {
+    let mut player: Player = Player::new(world);
+    player.camera.pos = vec2(3.5, 4.5);
+    for _ in 0..40 { player.move_by(world, vec2(0.05, 0.0), RADIUS); }
+    player.camera.pos.x
+}
+
+#[test]
+fn collision_in_each_state() {
+    let mut world: World = World::load_headless("tests/golden/level.map").unwrap();
+    assert!(world.solid(DOOR.0, DOOR.1));
+    assert!(walk_in(&world) <= 4.0 - RADIUS + 1e-4, "walked through a closed door");
+
+    world.use_door(DOOR, AWAY, RADIUS);
+    world.update(0.5, AWAY, RADIUS);
+    assert!(world.solid(DOOR.0, DOOR.1), "half open doors still block");
+    assert!(walk_in(&world) <= 4.0 - RADIUS + 1e-4);
+
+    world.update(0.5, AWAY, RADIUS);
+    assert!(!world.solid(DOOR.0, DOOR.1));
+    assert!(walk_in(&world) > 5.0, "couldn't walk through an open door");
+
+    world.use_door(DOOR, AWAY, RADIUS);
+    world.update(0.1, AWAY, RADIUS);
+    assert_eq!(state(&world).0, DoorState::Closing);
+    assert!(world.solid(DOOR.0, DOOR.1), "closing doors block");
+}
+
+// Distance to what the middle column ray hits from in front of the door
+fn center_ray(world: &World, y: f32) -> (f32, (i32, i32)) {
+    let camera: Camera = Camera::new(vec2(3.5, y), vec2(1.0, 0.0));
+    let mut ray: Ray = Ray::new(50.0, 100.0, &camera);
+    ray.dda(world);
+    assert!(ray.hit);
+    (ray.perp_wall_dist, ray.map)
+}
+
+#[test]
+fn rays_hit_the_closed_part() {
+    let mut world: World = World::load_headless("tests/golden/level.map").unwrap();
+    // The door is drawn across the middle of its cell
+    let (dist, cell): (f32, (i32, i32)) = center_ray(&world, 4.5);
+    assert!((dist - 1.0).abs() < 1e-4 && cell == DOOR, "{} {:?}", dist, cell);
+
+    // Half open, the door slides along y so the low half of the cell is clear
+    world.use_door(DOOR, AWAY, RADIUS);
+    world.update(0.5, AWAY, RADIUS);
+    let (dist, cell): (f32, (i32, i32)) = center_ray(&world, 4.25);
+    assert!(cell != DOOR && dist > 2.0, "hit the open half, {} {:?}", dist, cell);
+    let (dist, cell): (f32, (i32, i32)) = center_ray(&world, 4.75);
+    assert!((dist - 1.0).abs() < 1e-4 && cell == DOOR, "{} {:?}", dist, cell);
+}
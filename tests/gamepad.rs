@@ -0,0 +1,34 @@
+/*
+    Gamepad stick deadzone
+*/
+use macroquad::prelude::*;
+use raycast::{Controls, Stick, deadzone};
+
+#[test]
+fn inside_deadzone_is_zero() {
+    assert_eq!(deadzone(vec2(0.1, -0.05), 0.15), Vec2::ZERO);
+    assert_eq!(deadzone(vec2(0.0, 0.15), 0.15), Vec2::ZERO);
+    assert_eq!(deadzone(vec2(1.0, 0.0), 1.0), Vec2::ZERO);
+}
+
+#[test]
+fn rescales_outside_deadzone() {
+    // Halfway between the deadzone and the edge is half speed, in the same direction
+    let stick: Vec2 = deadzone(vec2(0.0, -0.6), 0.2);
+    assert!((stick - vec2(0.0, -0.5)).length() < 1e-5, "{:?}", stick);
+
+    let full: Vec2 = deadzone(vec2(1.0, 1.0).normalize(), 0.2);
+    assert!((full.length() - 1.0).abs() < 1e-5, "{:?}", full);
+    assert!((full.x - full.y).abs() < 1e-5, "{:?}", full);
+
+    // Square gates can report past 1 on the diagonals
+    assert!((deadzone(vec2(1.0, 1.0), 0.2).length() - 1.0).abs() < 1e-5);
+    assert_eq!(deadzone(vec2(0.3, 0.4), 0.0), vec2(0.3, 0.4));
+}
+
+#[test]
+fn no_gamepad_reads_zero() {
+    let controls: Controls = Controls::default();
+    assert_eq!(controls.stick(Stick::Left, 0.0), Vec2::ZERO);
+    assert_eq!(controls.stick(Stick::Right, 0.0), Vec2::ZERO);
+}
@@ -0,0 +1,126 @@
+/*
+    Golden image tests
+    Renders tests/golden/level.map from fixed camera poses with the software renderer and compares
+    each frame to the reference PNG next to it.
+    Run with UPDATE_GOLDEN=1 to write new references after an intended change to the rendering,
+    frames that don't match are written to target/golden/ for a look.
+*/
+use macroquad::prelude::*;
+use raycast::{Camera, Settings, World, SoftwareRenderer};
+
+const LEVEL: &str = "tests/golden/level.map";
+const WIDTH: f32 = 160.0;
+const HEIGHT: f32 = 120.0;
+const CHANNEL_TOLERANCE: u8 = 8; // Per channel difference allowed before a pixel counts as different
+const PIXEL_TOLERANCE: f32 = 0.002; // Fraction of pixels allowed to differ
+
+fn pose(x: f32, y: f32, height: f32, dir: Vec2, pitch: f32) -> Camera {
+    let mut camera: Camera = Camera::new(vec2(x, y), dir.normalize());
+    camera.height = height;
+    camera.pitch = pitch;
+    camera
+}
+
+fn check(name: &str, camera: Camera, setup: impl Fn(&mut Settings)) {
+    check_world(name, camera, &World::load_headless(LEVEL).unwrap(), setup);
+}
+
+fn check_world(name: &str, camera: Camera, world: &World, setup: impl Fn(&mut Settings)) {
+    let mut ds: Settings = Settings::new(WIDTH, HEIGHT, world.textures.len());
+    setup(&mut ds);
+
+    let mut renderer: SoftwareRenderer = SoftwareRenderer::new(&ds);
+    renderer.render(world, &ds, &camera);
+
+    let reference: String = format!("tests/golden/{}.png", name);
+    if std::env::var("UPDATE_GOLDEN").is_ok() {
+        renderer.save_png(&reference).unwrap();
+        return;
+    }
+
+    let expected = image::open(&reference)
+        .unwrap_or_else(|e| panic!("{}: {}, run with UPDATE_GOLDEN=1 to create it", reference, e))
+        .to_rgba8();
+    assert_eq!((expected.width(), expected.height()), (renderer.width as u32, renderer.height as u32), "{} has the wrong size", reference);
+
+    let different: usize = expected.as_raw().chunks_exact(4)
+        .zip(renderer.frame.chunks_exact(4))
+        .filter(|(a, b)| a.iter().zip(b.iter()).any(|(a, b)| a.abs_diff(*b) > CHANNEL_TOLERANCE))
+        .count();
+    let fraction: f32 = different as f32 / (renderer.width * renderer.height) as f32;
+
+    if fraction > PIXEL_TOLERANCE {
+        std::fs::create_dir_all("target/golden").unwrap();
+        let actual: String = format!("target/golden/{}.png", name);
+        renderer.save_png(&actual).unwrap();
+        panic!("{} differs from {} in {:.2}% of pixels, see {}", name, reference, fraction * 100.0, actual);
+    }
+}
+
+#[test]
+fn spawn() {
+    check("spawn", pose(1.5, 1.5, 0.0, vec2(1.0, 0.0), 0.0), |_| ());
+}
+
+#[test]
+fn corridor() {
+    check("corridor", pose(1.5, 8.5, 0.0, vec2(1.0, 0.0), 0.0), |_| ());
+}
+
+#[test]
+fn diagonal() {
+    check("diagonal", pose(3.0, 1.2, 0.0, vec2(1.0, 1.0), 0.0), |_| ());
+}
+
+#[test]
+fn pitch_and_height() {
+    check("pitch_and_height", pose(2.5, 3.5, 40.0, vec2(-1.0, 0.3), 45.0), |_| ());
+}
+
+#[test]
+fn look_down() {
+    check("look_down", pose(3.5, 6.5, -20.0, vec2(0.2, -1.0), -60.0), |_| ());
+}
+
+#[test]
+fn dark_shading() {
+    check("dark_shading", pose(3.0, 1.2, 0.0, vec2(1.0, 1.0), 0.0), |ds| ds.dark_shading = true);
+}
+
+#[test]
+fn sky() {
+    check("sky", pose(2.0, 2.0, 0.0, vec2(-1.0, 0.4), 80.0), |_| ());
+}
+
+#[test]
+fn nightvision() {
+    check("nightvision", pose(1.5, 1.5, 0.0, vec2(1.0, 0.0), 0.0), |ds| ds.nightvision = true);
+}
+
+#[test]
+fn push_wall() {
+    let mut world: World = World::load_headless(LEVEL).unwrap();
+    world.push((2, 6), (1, 0));
+    world.update(0.5, vec2(1.5, 1.5), 0.2);
+    check_world("push_wall", pose(1.5, 1.5, 0.0, vec2(1.0, 1.0), 0.0), &world, |_| ());
+}
+
+#[test]
+fn wall_heights() {
+    let world: World = World::load_headless("tests/golden/heights.map").unwrap();
+    check_world("wall_heights", pose(1.5, 3.5, 30.0, vec2(1.0, 0.5), -20.0), &world, |_| ());
+}
+
+#[test]
+fn texture_sizes() {
+    let world: World = World::load_headless("tests/golden/sizes.map").unwrap();
+    check_world("texture_sizes", pose(2.0, 1.2, 0.0, vec2(0.2, 1.0), -20.0), &world, |_| ());
+}
+
+#[test]
+fn half_open_door() {
+    let mut world: World = World::load_headless(LEVEL).unwrap();
+    world.use_door((4, 4), vec2(2.5, 4.5), 0.2);
+    world.update(0.5, vec2(2.5, 4.5), 0.2);
+    check_world("half_open_door", pose(2.5, 4.3, 0.0, vec2(1.0, 0.0), 0.0), &world, |_| ());
+}
@@ -0,0 +1,82 @@
+/*
+    Level file parsing, errors point at the line and column they were found at
+*/
+use macroquad::prelude::*;
+use raycast::Level;
+
+const LEVEL: &str = "\
+[level]
+manifest src/assets/manifest.txt
+[textures]
+1 red_brick
+[sprites]
+barrel 1.5 2.5
+[spawn]
+pos 1.5 1.5
+dir 0 1
+[map]
+1 1 1 1
+1 0 0 1
+1 1 1 1
+";
+
+fn error(source: &str) -> String {
+    match Level::parse(source) {
+        Err(e) => e.to_string(),
+        Ok(_) => panic!("parsed"),
+    }
+}
+
+// LEVEL with one line replaced
+fn replace(line: &str, with: &str) -> String {
+    assert!(LEVEL.contains(line), "{}", line);
+    LEVEL.replace(line, with)
+}
+
+#[test]
+fn parses_level() {
+    let level: Level = Level::parse(LEVEL).unwrap();
+    assert_eq!((level.width, level.height), (4, 3));
+    assert_eq!(level.world_map, vec![1, 1, 1, 1, 1, 0, 0, 1, 1, 1, 1, 1]);
+    assert!(level.floor_map.is_none() && level.ceil_map.is_none() && level.height_map.is_none());
+    assert_eq!(level.outside, 0);
+    assert_eq!(level.manifest, "src/assets/manifest.txt");
+    assert_eq!(level.textures.iter().map(|t| (t.name.as_str(), t.line, t.column)).collect::<Vec<_>>(), vec![("red_brick", 4, 3)]);
+    assert_eq!((level.sprites[0].name.name.as_str(), level.sprites[0].x, level.sprites[0].y), ("barrel", 1.5, 2.5));
+    assert_eq!((level.spawn.pos, level.spawn.dir), (vec2(1.5, 1.5), vec2(0.0, 1.0)));
+}
+
+#[test]
+fn bad_words() {
+    assert_eq!(error(&replace("1 0 0 1", "1 0 x 1")), "line 12, column 5: expected a texture id, found 'x'");
+    assert_eq!(error(&replace("1 0 0 1", "1 0 2 1")), "line 12, column 5: texture 2 is not defined in [textures]");
+    assert_eq!(error(&replace("[sprites]", "[sprite]")), "line 5, column 1: unknown section '[sprite]'");
+    assert_eq!(error(&replace("barrel 1.5 2.5", "barrel 1.5")), "line 6, column 11: expected '<name> <x> <y>'");
+    assert_eq!(error(&replace("barrel 1.5 2.5", "barrel 1.5 2.5 3.5")), "line 6, column 16: unexpected '3.5', expected '<name> <x> <y>'");
+    assert_eq!(error(&replace("pos 1.5 1.5", "pos 0.5 0.5")), "line 8, column 5: spawn (0.5, 0.5) is not an empty map cell");
+    // Columns count characters, not bytes
+    assert_eq!(error(&replace("barrel 1.5 2.5", "tönneau 1.5 zwei")), "line 6, column 13: expected a number, found 'zwei'");
+}
+
+#[test]
+fn ragged_rows() {
+    assert_eq!(error(&replace("1 0 0 1", "1 0 0 1 1")), "line 12, column 9: expected 4 cells in this row, found 5");
+    assert_eq!(error(&replace("1 0 0 1", "1 0 0")), "line 12, column 5: expected 4 cells in this row, found 3");
+    // Other grids have to match the map, the error points at their header
+    assert_eq!(error(&format!("{}[floor]\n1 1\n", LEVEL)), "line 14, column 1: expected 4x3 cells like the [map], found 2x1");
+}
+
+#[test]
+fn missing_sections() {
+    assert_eq!(error(LEVEL.split("[map]").next().unwrap()), "missing [map] section");
+    assert_eq!(error(&replace("[spawn]\npos 1.5 1.5\ndir 0 1\n", "")), "missing [spawn] section");
+    assert_eq!(error(&replace("manifest src/assets/manifest.txt\n", "")), "missing 'manifest <path>' in [level]");
+}
+
+#[test]
+fn non_finite_numbers() {
+    assert_eq!(error(&replace("barrel 1.5 2.5", "barrel nan 2.5")), "line 6, column 8: expected a finite number, found 'nan'");
+    assert_eq!(error(&replace("barrel 1.5 2.5", "barrel 1.5 -inf")), "line 6, column 12: expected a finite number, found '-inf'");
+    assert_eq!(error(&replace("pos 1.5 1.5", "pos inf 1.5")), "line 8, column 5: expected a finite number, found 'inf'");
+    assert_eq!(error(&replace("dir 0 1", "dir 0 NaN")), "line 9, column 7: expected a finite number, found 'NaN'");
+}
@@ -0,0 +1,69 @@
+/*
+    Asset manifest and texture names in levels
+*/
+use raycast::{Entity, Level, LevelError, Manifest, World};
+
+const LEVEL: &str = "\
+[level]
+manifest src/assets/manifest.txt
+[textures]
+1 red_brick
+2 button_off
+3 button_on
+[spawn]
+pos 1.5 1.5
+[entities]
+power button_on button_off 1 2
+[map]
+1 1 1 1
+1 0 3 1
+1 1 1 1
+";
+
+fn error(source: &str) -> String {
+    match Level::parse(source) {
+        Err(e) => e.to_string(),
+        Ok(_) => panic!("parsed"),
+    }
+}
+
+#[test]
+fn manifest_names() {
+    let manifest: Manifest = Manifest::parse("# Comment\nbrick  a/brick.png\n\nsky b/sky.png # Panorama\n").unwrap();
+    assert_eq!(manifest.path("brick"), Some("a/brick.png"));
+    assert_eq!(manifest.path("sky"), Some("b/sky.png"));
+    assert_eq!(manifest.path("moss"), None);
+    assert_eq!(manifest.names().collect::<Vec<&str>>(), vec!["brick", "sky"]);
+
+    let error = |source: &str| Manifest::parse(source).err().unwrap().to_string();
+    assert_eq!(error("brick a.png\n  brick b.png"), "line 2, column 3: 'brick' is defined twice");
+    assert_eq!(error("brick"), "line 1, column 1: expected '<name> <path>'");
+    assert_eq!(error("brick a.png b.png"), "line 1, column 13: expected '<name> <path>'");
+}
+
+#[test]
+fn power_uses_texture_names() {
+    let level: Level = Level::parse(LEVEL).unwrap();
+    assert!(matches!(level.entities[0], Entity::Power((3, 2), (1, 2))));
+
+    assert_eq!(error(&LEVEL.replace("power button_on", "power moss")), "line 10, column 7: 'moss' is not in [textures]");
+    assert_eq!(error(&LEVEL.replace("3 button_on", "3 button_off")), "line 6, column 3: 'button_off' is already texture 2");
+    assert!(matches!(Level::parse(&LEVEL.replace("manifest src/assets/manifest.txt", "")), Err(LevelError::NoManifest)));
+}
+
+#[test]
+fn names_resolve_through_the_registry() {
+    let world: World = World::load_headless("tests/golden/level.map").unwrap();
+    assert_eq!(world.textures.len(), 5);
+    assert_eq!(world.textures.id("mossy_cobble"), Some(3));
+    assert_eq!(world.textures.name(4), "metal_double_door");
+    assert_eq!(world.textures.id("moss"), None);
+}
+
+#[test]
+fn names_missing_from_the_manifest() {
+    let path: std::path::PathBuf = std::env::temp_dir().join("raycast_unknown_name.map");
+    std::fs::write(&path, LEVEL.replace("1 red_brick", "1 red_bricks")).unwrap();
+    let e: String = World::load_headless(path.to_str().unwrap()).err().unwrap().to_string();
+    assert_eq!(e, "line 4, column 3: 'red_bricks' is not in the manifest src/assets/manifest.txt");
+}
@@ -0,0 +1,66 @@
+/*
+    Pack files and loading levels out of them
+*/
+use raycast::{AssetSource, Pack, World};
+
+fn file(path: &str) -> (String, Vec<u8>) {
+    (path.to_string(), std::fs::read(path).unwrap())
+}
+
+#[test]
+fn round_trips() {
+    let files: Vec<(String, Vec<u8>)> = vec![(String::from("a.txt"), b"hello".to_vec()), (String::from("dir/b"), Vec::new())];
+    let pack: Pack = Pack::parse(Pack::build(&files)).unwrap();
+    assert_eq!(pack.names().collect::<Vec<&str>>(), vec!["a.txt", "dir/b"]);
+    assert_eq!(pack.get("a.txt"), Some(&b"hello"[..]));
+    assert_eq!(pack.get("dir/b"), Some(&[][..]));
+    assert_eq!(pack.get("c"), None);
+}
+
+#[test]
+fn rejects_broken_packs() {
+    let data: Vec<u8> = Pack::build(&[(String::from("a.txt"), b"hello".to_vec())]);
+    assert_eq!(Pack::parse(b"PK\x03\x04 a zip".to_vec()).err().unwrap().to_string(), "not a pack file");
+    assert_eq!(Pack::parse(data[..data.len() - 1].to_vec()).err().unwrap().to_string(), "pack entry a.txt is past the end of the pack");
+    assert_eq!(Pack::parse(data[..10].to_vec()).err().unwrap().to_string(), "pack ends early");
+}
+
+#[test]
+fn loads_a_level_from_a_pack() {
+    let mut files: Vec<(String, Vec<u8>)> = vec![file("tests/golden/level.map"), file("src/assets/manifest.txt")];
+    for name in ["red_brick", "concrete_pattern", "mossy_cobble", "metal_double_door", "wood_plank", "barrel", "pillar", "greenlight", "sky"] {
+        files.push(file(&format!("src/assets/{}.png", name)));
+    }
+    let assets: AssetSource = AssetSource::Pack(Pack::parse(Pack::build(&files)).unwrap());
+    let (packed, missing) = World::load_with_placeholders(&assets, "tests/golden/level.map").unwrap();
+    assert!(missing.is_empty());
+
+    let loose: World = World::load_headless("tests/golden/level.map").unwrap();
+    assert_eq!(packed.world_map, loose.world_map);
+    assert_eq!(packed.texture(3).texture_data, loose.texture(3).texture_data);
+    assert_eq!(packed.sprite_textures.len(), loose.sprite_textures.len());
+
+    // Anything not packed is missing rather than read from disk
+    files.retain(|(name, _)| name != "src/assets/pillar.png");
+    let assets: AssetSource = AssetSource::Pack(Pack::parse(Pack::build(&files)).unwrap());
+    let (_, missing) = World::load_with_placeholders(&assets, "tests/golden/level.map").unwrap();
+    assert_eq!(missing.iter().map(|e| e.to_string()).collect::<Vec<String>>(), vec!["couldn't read src/assets/pillar.png: not in the pack"]);
+}
+
+#[test]
+fn layers_prefer_the_first_source() {
+    const BUILT_IN: &[(&str, &[u8])] = &[("src/assets/manifest.txt", b"built in"), ("only/built/in.txt", b"here")];
+    let assets: AssetSource = AssetSource::Layers(vec![AssetSource::default(), AssetSource::Embedded(BUILT_IN)]);
+    assert_eq!(assets.read("src/assets/manifest.txt").unwrap(), std::fs::read("src/assets/manifest.txt").unwrap());
+    assert_eq!(assets.read("only/built/in.txt").unwrap(), b"here");
+    assert_eq!(assets.read("nowhere.txt").err().unwrap().to_string(), "not built into the game");
+}
+
+#[cfg(feature = "embedded-assets")]
+#[test]
+fn demo_is_built_in() {
+    let assets: AssetSource = AssetSource::Embedded(raycast::assets::assets::EMBEDDED);
+    let (world, missing) = World::load_with_placeholders(&assets, "src/assets/levels/demo.map").unwrap();
+    assert!(missing.is_empty(), "{:?}", missing.iter().map(|e| e.to_string()).collect::<Vec<String>>());
+    assert_eq!(world.textures.len(), 14);
+}
@@ -0,0 +1,124 @@
+/*
+    Push walls sliding, stopping early and being pushed again from where they stopped
+*/
+use macroquad::prelude::*;
+use raycast::{AssetSource, Camera, Player, Settings, World};
+
+const RADIUS: f32 = 0.2;
+
+// The wall at (2, 2) can slide 3 cells down the room towards row 5
+const ROOM: &str = "\
+[level]
+manifest src/assets/manifest.txt
+[textures]
+1 red_brick
+2 concrete_pattern
+3 mossy_cobble
+[entities]
+pushwall 2 2 3
+[spawn]
+pos 1.5 2.5
+[map]
+1 1 1 1 1
+1 0 0 0 1
+1 0 3 0 1
+1 0 0 0 1
+1 0 0 0 1
+1 0 0 0 1
+1 1 1 1 1
+";
+
+// The same wall with a door across the way at (4, 2)
+const DOORWAY: &str = "\
+[level]
+manifest src/assets/manifest.txt
+[textures]
+1 red_brick
+2 concrete_pattern
+3 mossy_cobble
+4 metal_double_door
+[entities]
+pushwall 2 2 3
+door 4 2
+[spawn]
+pos 1.5 2.5
+[map]
+1 1 1 1 1
+1 0 0 0 1
+1 0 3 0 1
+1 0 0 0 1
+1 1 4 1 1
+1 0 0 0 1
+1 1 1 1 1
+";
+
+// Levels are read from memory, the manifest and images from the repository
+fn load(source: &'static str) -> World {
+    let files: &'static [(&'static str, &'static [u8])] = Box::leak(Box::new([("test.map", source.as_bytes())]));
+    let assets: AssetSource = AssetSource::Layers(vec![AssetSource::Embedded(files), AssetSource::default()]);
+    World::load_with_placeholders(&assets, "test.map").unwrap().0
+}
+
+// Interact with whatever is in front of a player at pos facing down the room
+fn interact(world: &mut World, pos: Vec2) {
+    let mut ds: Settings = Settings::new(640.0, 480.0, world.textures.len());
+    let mut player: Player = Player::new(world);
+    player.camera = Camera::new(pos, vec2(1.0, 0.0));
+    player.interact(world, &mut ds);
+}
+
+#[test]
+fn pushed_again_after_being_blocked() {
+    let mut world: World = load(ROOM);
+    interact(&mut world, vec2(1.5, 2.5));
+    assert!(world.push_walls[0].moving);
+    assert_eq!(world.get(2, 2), 0, "left the wall behind in the map");
+
+    // Someone standing in row 4 stops it after one cell
+    world.update(1.0, vec2(4.5, 2.5), RADIUS);
+    let wall = &world.push_walls[0];
+    assert_eq!((wall.cell, wall.cells, wall.moving), ((3, 2), 2, false));
+    assert_eq!(world.get(3, 2), 3);
+    assert!(world.solid(3, 2) && !world.solid(2, 2));
+
+    // Pushing it where it stopped sends it the rest of the way
+    interact(&mut world, vec2(2.5, 2.5));
+    assert!(world.push_walls[0].moving, "couldn't push it again");
+    world.update(1.0, vec2(2.5, 2.5), RADIUS);
+    world.update(1.0, vec2(2.5, 2.5), RADIUS);
+    let wall = &world.push_walls[0];
+    assert_eq!((wall.cell, wall.cells, wall.moving), ((5, 2), 0, false));
+
+    // Only the cell it ended up in blocks
+    assert_eq!(world.get(5, 2), 3);
+    for r in 2..5 { assert!(world.get(r, 2) == 0 && !world.solid(r, 2), "row {} still blocks", r); }
+    assert!(world.solid(5, 2));
+    let mut player: Player = Player::new(&world);
+    player.camera.pos = vec2(3.5, 2.5);
+    for _ in 0..40 { player.move_by(&world, vec2(0.05, 0.0), RADIUS); }
+    assert!(player.camera.pos.x > 4.5 && player.camera.pos.x <= 5.0 - RADIUS + 1e-4, "{}", player.camera.pos.x);
+
+    // Out of cells, so it stays put
+    interact(&mut world, vec2(4.5, 2.5));
+    assert!(!world.push_walls[0].moving);
+}
+
+#[test]
+fn doors_stop_push_walls() {
+    let mut world: World = load(DOORWAY);
+    world.use_door((4, 2), vec2(1.5, 1.5), RADIUS);
+    world.update(1.0, vec2(1.5, 1.5), RADIUS);
+    assert!(!world.solid(4, 2));
+
+    // Stops short of the open door instead of sliding into it
+    interact(&mut world, vec2(1.5, 2.5));
+    world.update(1.0, vec2(1.5, 2.5), RADIUS);
+    let wall = &world.push_walls[0];
+    assert_eq!((wall.cell, wall.cells, wall.moving), ((3, 2), 2, false));
+    assert_eq!(world.get(4, 2), 4);
+
+    // And won't start moving into one either
+    interact(&mut world, vec2(2.5, 2.5));
+    assert!(!world.push_walls[0].moving);
+    assert_eq!(world.get(3, 2), 3);
+}
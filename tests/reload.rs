@@ -0,0 +1,60 @@
+/*
+    Hot reloading, watching files and reloading textures in place
+*/
+use raycast::{AssetSource, Texture, Watcher, World};
+
+// Scratch directory with a copy of the golden level and its assets, so files can be changed
+fn scratch(name: &str) -> std::path::PathBuf {
+    let dir: std::path::PathBuf = std::env::temp_dir().join(format!("raycast_reload_{}", name));
+    let _ = std::fs::remove_dir_all(&dir);
+    for file in ["tests/golden/level.map", "src/assets/manifest.txt"] {
+        std::fs::create_dir_all(dir.join(file).parent().unwrap()).unwrap();
+        std::fs::copy(file, dir.join(file)).unwrap();
+    }
+    for entry in std::fs::read_dir("src/assets").unwrap() {
+        let path: std::path::PathBuf = entry.unwrap().path();
+        if path.extension().is_some_and(|e| e == "png") { std::fs::copy(&path, dir.join(&path)).unwrap(); }
+    }
+    dir
+}
+
+#[test]
+fn watches_for_changes() {
+    let dir: std::path::PathBuf = scratch("watch");
+    let files: Vec<String> = vec![String::from("tests/golden/level.map"), String::from("src/assets/new.png")];
+    let mut watcher: Watcher = Watcher::new(&dir, &files);
+    assert!(watcher.changed().is_empty());
+
+    // Modified times can be coarse, set one rather than waiting for the clock
+    let level: std::fs::File = std::fs::OpenOptions::new().append(true).open(dir.join("tests/golden/level.map")).unwrap();
+    level.set_modified(std::time::SystemTime::now() + std::time::Duration::from_secs(10)).unwrap();
+    std::fs::write(dir.join("src/assets/new.png"), b"").unwrap();
+    assert_eq!(watcher.changed(), files);
+    assert!(watcher.changed().is_empty());
+
+    // Polling waits a moment between checks
+    std::fs::remove_file(dir.join("src/assets/new.png")).unwrap();
+    assert!(watcher.poll(0.1).is_empty());
+    assert_eq!(watcher.poll(1.0), vec![String::from("src/assets/new.png")]);
+}
+
+#[test]
+fn reloads_textures_in_place() {
+    let dir: std::path::PathBuf = scratch("textures");
+    let assets: AssetSource = AssetSource::Dir(dir.clone());
+    let (mut world, _) = World::load_with_placeholders(&assets, "tests/golden/level.map").unwrap();
+    assert_eq!(world.files[..3], [String::from("tests/golden/level.map"), String::from("src/assets/manifest.txt"), String::from("src/assets/barrel.png")]);
+
+    // Swap the brick for the moss, only the texture loaded from that file changes
+    std::fs::copy("src/assets/mossy_cobble.png", dir.join("src/assets/red_brick.png")).unwrap();
+    assert_eq!(world.reload_texture(&assets, "src/assets/red_brick.png").unwrap(), 1);
+    let moss: Texture = Texture::load(&AssetSource::default(), "src/assets/mossy_cobble.png").unwrap();
+    assert_eq!(world.texture(1).texture_data, moss.texture_data);
+    assert_eq!(world.texture(1).path, "src/assets/red_brick.png");
+    assert_eq!(world.reload_texture(&assets, "src/assets/unused.png").unwrap(), 0);
+
+    // A broken file keeps the old texture
+    std::fs::write(dir.join("src/assets/red_brick.png"), b"not a png").unwrap();
+    assert!(world.reload_texture(&assets, "src/assets/red_brick.png").is_err());
+    assert_eq!(world.texture(1).texture_data, moss.texture_data);
+}
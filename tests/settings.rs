@@ -0,0 +1,68 @@
+/*
+    Settings config parsing
+*/
+use raycast::Settings;
+
+#[test]
+fn round_trips() {
+    let mut ds: Settings = Settings::new(640.0, 480.0, 4);
+    ds.move_speed = 6.5;
+    ds.headbob = false;
+    ds.ceil_texture = 3;
+    ds.resolution_x = 320.0;
+    ds.gamepad_deadzone = 0.1;
+
+    let mut parsed: Settings = Settings::new(640.0, 480.0, 4);
+    let warnings: Vec<String> = parsed.parse(&ds.to_config());
+    assert!(warnings.is_empty(), "{:?}", warnings);
+    assert_eq!(parsed.to_config(), ds.to_config());
+    assert_eq!(parsed.move_speed, 6.5);
+    assert!(!parsed.headbob);
+    assert_eq!(parsed.ceil_texture, 3);
+}
+
+#[test]
+fn bad_lines_keep_defaults() {
+    let mut ds: Settings = Settings::new(640.0, 480.0, 4);
+    let source: &str = "\
+# Comment
+move_speed = 7   # faster
+rot_speed = 100
+look_speed = fast
+floor_texture = 4
+headbob = maybe
+player_radius = NaN
+gravity = 9.8
+shaders
+";
+    let warnings: Vec<String> = ds.parse(source);
+    assert_eq!(warnings, vec![
+        "line 3: invalid rot_speed '100', expected a number from 1 to 10",
+        "line 4: invalid look_speed 'fast', expected a number from 100 to 300",
+        "line 5: invalid floor_texture '4', expected a number from 0 to 3",
+        "line 6: invalid headbob 'maybe', expected true or false",
+        "line 7: invalid player_radius 'NaN', expected a number from 0.05 to 0.45",
+        "line 8: unknown setting 'gravity'",
+        "line 9: expected '<setting> = <value>'",
+    ]);
+
+    let defaults: Settings = Settings::new(640.0, 480.0, 4);
+    assert_eq!(ds.move_speed, 7.0);
+    assert_eq!(ds.rot_speed, defaults.rot_speed);
+    assert_eq!(ds.look_speed, defaults.look_speed);
+    assert_eq!(ds.floor_texture, defaults.floor_texture);
+    assert_eq!(ds.headbob, defaults.headbob);
+    assert_eq!(ds.player_radius, defaults.player_radius);
+}
+
+#[test]
+fn default_textures_fit_the_level() {
+    let ds: Settings = Settings::new(640.0, 480.0, 4);
+    assert_eq!((ds.floor_texture, ds.ceil_texture), (1, 2));
+    let ds: Settings = Settings::new(640.0, 480.0, 2);
+    assert_eq!((ds.floor_texture, ds.ceil_texture), (1, 1));
+    let ds: Settings = Settings::new(640.0, 480.0, 1);
+    assert_eq!((ds.floor_texture, ds.ceil_texture), (0, 0));
+    let ds: Settings = Settings::new(640.0, 480.0, 0);
+    assert_eq!((ds.floor_texture, ds.ceil_texture), (0, 0));
+}
@@ -0,0 +1,55 @@
+/*
+    Rendering on worker threads has to give the same frame as rendering on one
+*/
+use macroquad::prelude::*;
+use std::thread::ThreadId;
+use raycast::{Camera, Settings, World, SoftwareRenderer};
+
+const LEVEL: &str = "tests/golden/level.map";
+
+fn render(world: &World, camera: &Camera, threads: usize, setup: impl Fn(&mut Settings)) -> Vec<u8> {
+    let mut ds: Settings = Settings::new(157.0, 103.0, world.textures.len()); // Odd sizes so chunks don't split evenly
+    ds.threads = threads;
+    setup(&mut ds);
+    let mut renderer: SoftwareRenderer = SoftwareRenderer::new(&ds);
+    renderer.render(world, &ds, camera);
+    renderer.frame
+}
+
+#[test]
+fn threads_match_single_threaded() {
+    let world: World = World::load_headless(LEVEL).unwrap();
+    let mut camera: Camera = Camera::new(vec2(2.3, 1.7), vec2(1.0, 0.4).normalize());
+    camera.pitch = 25.0;
+    camera.height = -10.0;
+
+    for setup in [|_: &mut Settings| (), |ds: &mut Settings| ds.nightvision = true, |ds: &mut Settings| ds.dark_shading = true] {
+        let expected: Vec<u8> = render(&world, &camera, 1, setup);
+        for threads in [2, 3, 8, 200] {
+            assert!(render(&world, &camera, threads, setup) == expected, "{} threads differ from 1", threads);
+        }
+    }
+}
+
+#[test]
+fn workers_are_reused_across_frames() {
+    let world: World = World::load_headless(LEVEL).unwrap();
+    let camera: Camera = Camera::new(vec2(2.3, 1.7), vec2(1.0, 0.4).normalize());
+    let expected: Vec<u8> = render(&world, &camera, 1, |_| ());
+
+    // One renderer keeps its threads while the thread count goes up and down between frames,
+    // the calling thread draws too so n threads only ever needs n - 1 workers
+    let mut ds: Settings = Settings::new(157.0, 103.0, world.textures.len());
+    let mut renderer: SoftwareRenderer = SoftwareRenderer::new(&ds);
+    let mut started: Vec<ThreadId> = Vec::new();
+    for (threads, workers) in [(4, 3), (2, 3), (1, 3), (7, 6), (3, 6), (4, 6)] {
+        ds.threads = threads;
+        renderer.render(&world, &ds, &camera);
+        assert!(renderer.frame == expected, "{} threads differ from 1", threads);
+
+        let now: Vec<ThreadId> = renderer.workers().threads();
+        assert_eq!(now.len(), workers, "workers after a frame on {} threads", threads);
+        assert!(now.starts_with(&started), "threads from earlier frames were replaced on {} threads", threads);
+        started = now;
+    }
+}